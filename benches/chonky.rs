@@ -1,3 +1,5 @@
+#![allow(clippy::unnecessary_cast)]
+
 use smolldb::SmollDB;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
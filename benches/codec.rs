@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use smolldb::SmollDB;
+
+fn database() -> SmollDB {
+    let mut db = SmollDB::default();
+    for i in 0..1000 {
+        db.set(format!("bool{}", i), i % 2 == 0);
+        db.set(format!("int8{}", i), i as i8);
+        db.set(format!("int16{}", i), i as i16);
+        db.set(format!("int32{}", i), i);
+        db.set(format!("int64{}", i), i as i64);
+        db.set(format!("float32{}", i), i as f32);
+        db.set(format!("float64{}", i), i as f64);
+        db.set(format!("string{}", i), format!("string{}", i));
+        db.set(format!("bytes{}", i), vec![i as u8; 100]);
+    }
+    db
+}
+
+fn encode(c: &mut Criterion) {
+    let db = database();
+    let mut encoded_data = Vec::new();
+    c.bench_function("encode", |b| {
+        b.iter(|| {
+            encoded_data.clear();
+            db.backup_to_stream(black_box(&mut encoded_data)).unwrap();
+        });
+    });
+}
+
+fn decode(c: &mut Criterion) {
+    let db = database();
+    let mut encoded_data = Vec::new();
+    db.backup_to_stream(&mut encoded_data).unwrap();
+    c.bench_function("decode", |b| {
+        b.iter(|| SmollDB::load_from_stream(&mut black_box(encoded_data.as_slice())).unwrap());
+    });
+}
+
+criterion_group!(benches, encode, decode);
+criterion_main!(benches);
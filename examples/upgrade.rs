@@ -0,0 +1,50 @@
+//!Upgrade .smoll files to the latest format version
+//!
+//!```text
+//!cargo run --example upgrade -- old.smoll [new.smoll]
+//!```
+//!Without a destination the file is upgraded in place
+use smolldb::{SmollDB, FORMAT_VERSION};
+use std::{env, process::ExitCode};
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let (src, dst) = match (args.next(), args.next()) {
+        (Some(src), dst) => {
+            let dst = dst.unwrap_or_else(|| src.clone());
+            (src, dst)
+        }
+        (None, _) => {
+            eprintln!("usage: upgrade <source> [destination]");
+            return ExitCode::FAILURE;
+        }
+    };
+    let version = match SmollDB::format_version(&src) {
+        Ok(version) => version,
+        Err(err) => {
+            eprintln!("{}: {:?}", src, err);
+            return ExitCode::FAILURE;
+        }
+    };
+    if version > FORMAT_VERSION {
+        eprintln!(
+            "{}: format version {} is newer than this tool",
+            src, version
+        );
+        return ExitCode::FAILURE;
+    }
+    if version == FORMAT_VERSION && src == dst {
+        println!("{} already uses format version {}", src, version);
+        return ExitCode::SUCCESS;
+    }
+    match SmollDB::migrate(&src, &dst) {
+        Ok(()) => {
+            println!("{}: format version {} -> {}", dst, version, FORMAT_VERSION);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}: {:?}", src, err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Error, Result};
+use std::io::{self, Read, Write};
+use yazi::{CompressionLevel, Decoder, Encoder, Format};
+
+///Algorithm used to compress the records of a .smoll file, chosen with [`BackupOptions::compression`](crate::BackupOptions::compression)
+///
+///The algorithm is recorded in the file header, so loading picks the right one automatically.
+///`Zstd` and `Lz4` are only available with the `zstd` and `lz4` cargo features
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Compression {
+    ///Records are stored as they are, for tiny databases or data that is already compressed
+    None,
+    ///[Zlib](https://en.wikipedia.org/wiki/Zlib) compatible compression
+    #[default]
+    Zlib,
+    ///[Zstandard](https://en.wikipedia.org/wiki/Zstd) compression
+    #[cfg(feature = "zstd")]
+    Zstd,
+    ///[LZ4](https://en.wikipedia.org/wiki/LZ4_(compression_algorithm)) frame compression, the level is ignored
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+///Header flag bits holding the id of the [`Compression`]
+pub(crate) const COMPRESSION_FLAGS: u16 = 0x000F;
+
+const ZLIB_ID: u16 = 0;
+const NONE_ID: u16 = 1;
+#[cfg_attr(not(feature = "zstd"), allow(dead_code))]
+const ZSTD_ID: u16 = 2;
+#[cfg_attr(not(feature = "lz4"), allow(dead_code))]
+const LZ4_ID: u16 = 3;
+
+impl Compression {
+    ///Id stored in the header flags
+    pub(crate) fn id(&self) -> u16 {
+        match self {
+            Compression::None => NONE_ID,
+            Compression::Zlib => ZLIB_ID,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => ZSTD_ID,
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => LZ4_ID,
+        }
+    }
+
+    ///Find the algorithm recorded in the header `flags`, failing if it is unknown
+    ///or its cargo feature is disabled
+    pub(crate) fn from_flags(flags: u16) -> Result<Self> {
+        match flags & COMPRESSION_FLAGS {
+            NONE_ID => Ok(Compression::None),
+            ZLIB_ID => Ok(Compression::Zlib),
+            #[cfg(feature = "zstd")]
+            ZSTD_ID => Ok(Compression::Zstd),
+            #[cfg(feature = "lz4")]
+            LZ4_ID => Ok(Compression::Lz4),
+            id => Err(Error::UnsupportedCompression(id as u8)),
+        }
+    }
+
+    ///Compress everything `write` produces into `stream`.
+    ///Without a `level` the fastest one of the algorithm is used
+    pub(crate) fn compress(
+        &self,
+        level: Option<u8>,
+        stream: &mut impl Write,
+        write: impl FnOnce(&mut dyn Write) -> Result<()>,
+    ) -> Result<()> {
+        match self {
+            Compression::None => write(stream),
+            Compression::Zlib => {
+                let mut encoder = Encoder::boxed();
+                encoder.set_format(Format::Zlib);
+                encoder.set_level(match level {
+                    Some(level) => CompressionLevel::Specific(level),
+                    None => CompressionLevel::BestSpeed,
+                });
+                let mut compressed_stream = encoder.stream(stream);
+                write(&mut compressed_stream)?;
+                compressed_stream.finish()?;
+                Ok(())
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(stream, level.unwrap_or(1).into())?;
+                write(&mut encoder)?;
+                encoder.finish()?;
+                Ok(())
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                let mut encoder = lz4_flex::frame::FrameEncoder::new(stream);
+                write(&mut encoder)?;
+                encoder.finish().map_err(io::Error::from)?;
+                Ok(())
+            }
+        }
+    }
+
+    ///Decompress `stream` into `sink` a piece at a time
+    pub(crate) fn decompress(&self, stream: &mut impl Read, sink: &mut impl Write) -> Result<()> {
+        match self {
+            Compression::None => {
+                io::copy(stream, sink)?;
+            }
+            Compression::Zlib => {
+                let mut decoder = Decoder::boxed();
+                decoder.set_format(Format::Zlib);
+                let mut decompressed_stream = decoder.stream(sink);
+                io::copy(stream, &mut decompressed_stream)?;
+                decompressed_stream.finish()?;
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                io::copy(&mut zstd::Decoder::new(stream)?, sink)?;
+            }
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => {
+                io::copy(&mut lz4_flex::frame::FrameDecoder::new(stream), sink)?;
+            }
+        }
+        Ok(())
+    }
+}
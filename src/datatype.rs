@@ -1,4 +1,8 @@
-use std::fmt::{Debug, Display};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Debug, Display},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use crate::Error;
 
@@ -32,6 +36,60 @@ impl From<i64> for DataType {
     }
 }
 
+impl From<u8> for DataType {
+    fn from(value: u8) -> Self {
+        Self::UINT8(value)
+    }
+}
+
+impl From<u16> for DataType {
+    fn from(value: u16) -> Self {
+        Self::UINT16(value)
+    }
+}
+
+impl From<u32> for DataType {
+    fn from(value: u32) -> Self {
+        Self::UINT32(value)
+    }
+}
+
+impl From<u64> for DataType {
+    fn from(value: u64) -> Self {
+        Self::UINT64(value)
+    }
+}
+
+impl From<i128> for DataType {
+    fn from(value: i128) -> Self {
+        Self::INT128(value)
+    }
+}
+
+impl From<u128> for DataType {
+    fn from(value: u128) -> Self {
+        Self::UINT128(value)
+    }
+}
+
+impl From<char> for DataType {
+    fn from(value: char) -> Self {
+        Self::CHAR(value)
+    }
+}
+
+impl From<SystemTime> for DataType {
+    fn from(value: SystemTime) -> Self {
+        Self::TIMESTAMP(value)
+    }
+}
+
+impl From<Duration> for DataType {
+    fn from(value: Duration) -> Self {
+        Self::DURATION(value)
+    }
+}
+
 impl From<f32> for DataType {
     fn from(value: f32) -> Self {
         Self::FLOAT32(value)
@@ -56,6 +114,89 @@ impl From<Vec<u8>> for DataType {
     }
 }
 
+impl<T: Into<DataType>> From<Option<T>> for DataType {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => value.into(),
+            None => Self::NULL,
+        }
+    }
+}
+
+///Conversion of lists of every type except `u8`, which convert to [`BYTES`](DataType::BYTES) instead
+macro_rules! impl_try_from_list {
+    ($($type_name:ty),*) => {
+        $(
+            impl TryFrom<&DataType> for Vec<$type_name> {
+                type Error = Error;
+                fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+                    match value {
+                        DataType::LIST(inner_value) => {
+                            inner_value.iter().map(<$type_name>::try_from).collect()
+                        }
+                        _ => Err(Error::ConversionError(value.clone())),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_list! {
+    bool, i8, i16, i32, i64, u16, u32, u64, i128, u128, f32, f64, char, String, Vec<u8>,
+    SystemTime, Duration
+}
+
+///Conversion into lists, integers are left out so `vec![1, 2, 3]` keeps converting to
+///[`BYTES`](DataType::BYTES), collect them into a `DataType` instead
+macro_rules! impl_from_list {
+    ($($type_name:ty),*) => {
+        $(
+            impl From<Vec<$type_name>> for DataType {
+                fn from(value: Vec<$type_name>) -> Self {
+                    value.into_iter().collect()
+                }
+            }
+        )*
+    };
+}
+
+impl_from_list!(bool, f32, f64, char, String, Vec<u8>, SystemTime, Duration);
+
+impl<T: Into<DataType>> FromIterator<T> for DataType {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::LIST(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Vec<DataType>> for DataType {
+    fn from(value: Vec<DataType>) -> Self {
+        Self::LIST(value)
+    }
+}
+
+impl<T: Into<DataType>> From<BTreeMap<String, T>> for DataType {
+    fn from(value: BTreeMap<String, T>) -> Self {
+        Self::MAP(
+            value
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: Into<DataType>> From<HashMap<String, T>> for DataType {
+    fn from(value: HashMap<String, T>) -> Self {
+        Self::MAP(
+            value
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
 ///A union for all the types supported by smolldb
 ///
 ///Supported types are:
@@ -64,10 +205,25 @@ impl From<Vec<u8>> for DataType {
 ///* `i16`
 ///* `i32`
 ///* `i64`
+///* `u8`
+///* `u16`
+///* `u32`
+///* `u64`
+///* `i128`
+///* `u128`
 ///* `f32`
 ///* `f64`
+///* `char`
 ///* `String`
 ///* `Vec<u8>`
+///* `SystemTime`
+///* `Duration`
+///
+///[`NULL`](DataType::NULL) stores a key without a value, `None` converts to it.
+///[`LIST`](DataType::LIST) holds values of any type, build it by collecting them into a `DataType`.
+///`Vec<u8>` is stored as [`BYTES`](DataType::BYTES) instead.
+///[`MAP`](DataType::MAP) holds named values of any type, in key order so backups stay reproducible.
+///Lists and maps can be nested up to 64 levels deep
 #[derive(PartialEq, Debug, Clone)]
 pub enum DataType {
     BOOL(bool),
@@ -75,10 +231,22 @@ pub enum DataType {
     INT16(i16),
     INT32(i32),
     INT64(i64),
+    UINT8(u8),
+    UINT16(u16),
+    UINT32(u32),
+    UINT64(u64),
+    INT128(i128),
+    UINT128(u128),
     FLOAT32(f32),
     FLOAT64(f64),
+    CHAR(char),
     STRING(String),
     BYTES(Vec<u8>),
+    NULL,
+    LIST(Vec<DataType>),
+    MAP(BTreeMap<String, DataType>),
+    TIMESTAMP(SystemTime),
+    DURATION(Duration),
 }
 
 impl Display for DataType{
@@ -93,10 +261,119 @@ impl Display for DataType{
             DataType::FLOAT64(value) => write!(f, "{}", value),
             DataType::STRING(value) => write!(f, "{}", value),
             DataType::BYTES(value) => write!(f, "{:?}", value),
+            DataType::UINT8(value) => write!(f, "{}", value),
+            DataType::UINT16(value) => write!(f, "{}", value),
+            DataType::UINT32(value) => write!(f, "{}", value),
+            DataType::UINT64(value) => write!(f, "{}", value),
+            DataType::INT128(value) => write!(f, "{}", value),
+            DataType::UINT128(value) => write!(f, "{}", value),
+            DataType::CHAR(value) => write!(f, "{}", value),
+            DataType::NULL => write!(f, "null"),
+            DataType::LIST(value) => {
+                write!(f, "[")?;
+                for (index, value) in value.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            DataType::MAP(value) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in value.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            }
+            DataType::TIMESTAMP(value) => {
+                let (seconds, nanos) = timestamp_parts(value);
+                let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+                let seconds = seconds.rem_euclid(86400);
+                //Years outside 0000-9999 need the expanded ISO 8601 form, with a sign
+                if (0..=9999).contains(&year) {
+                    write!(f, "{:04}", year)?;
+                } else {
+                    write!(f, "{:+05}", year)?;
+                }
+                write!(
+                    f,
+                    "-{:02}-{:02}T{:02}:{:02}:{:02}",
+                    month,
+                    day,
+                    seconds / 3600,
+                    seconds / 60 % 60,
+                    seconds % 60
+                )?;
+                write_fraction(f, nanos)?;
+                write!(f, "Z")
+            }
+            DataType::DURATION(value) => {
+                write!(f, "PT{}", value.as_secs())?;
+                write_fraction(f, value.subsec_nanos())?;
+                write!(f, "S")
+            }
         }
     }
 }
 
+///Seconds since the Unix epoch, negative before it, and the nanoseconds past that second.
+///Computed on 128 bits, so every [`SystemTime`] fits
+pub(crate) fn timestamp_parts(value: &SystemTime) -> (i128, u32) {
+    match value.duration_since(UNIX_EPOCH) {
+        Ok(elapsed) => (elapsed.as_secs().into(), elapsed.subsec_nanos()),
+        Err(err) => {
+            let before = err.duration();
+            let seconds = -i128::from(before.as_secs());
+            match before.subsec_nanos() {
+                0 => (seconds, 0),
+                nanos => (seconds - 1, 1_000_000_000 - nanos),
+            }
+        }
+    }
+}
+
+///Inverse of [`timestamp_parts`], `None` if the parts are invalid or out of the range of [`SystemTime`]
+pub(crate) fn timestamp_from_parts(seconds: i64, nanos: u32) -> Option<SystemTime> {
+    if nanos >= 1_000_000_000 {
+        return None;
+    }
+    let nanos = Duration::from_nanos(nanos.into());
+    match u64::try_from(seconds) {
+        Ok(seconds) => UNIX_EPOCH.checked_add(Duration::from_secs(seconds) + nanos),
+        Err(_) => UNIX_EPOCH
+            .checked_sub(Duration::from_secs(seconds.unsigned_abs()))?
+            .checked_add(nanos),
+    }
+}
+
+///Year, month and day of the proleptic Gregorian calendar `days` after the Unix epoch
+fn civil_from_days(days: i128) -> (i128, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i128::from(month <= 2);
+    (year, month, day)
+}
+
+///Write the fraction of a second, without trailing zeros and omitted if zero
+fn write_fraction(f: &mut std::fmt::Formatter<'_>, nanos: u32) -> std::fmt::Result {
+    if nanos == 0 {
+        return Ok(());
+    }
+    let fraction = format!("{:09}", nanos);
+    write!(f, ".{}", fraction.trim_end_matches('0'))
+}
+
 impl DataType {
     ///get the id of the current type stored in this Datatype obj
     ///*used for the encoding step*
@@ -111,6 +388,18 @@ impl DataType {
             DataType::FLOAT64(_) => 6,
             DataType::STRING(_) => 7,
             DataType::BYTES(_) => 8,
+            DataType::UINT8(_) => 9,
+            DataType::UINT16(_) => 10,
+            DataType::UINT32(_) => 11,
+            DataType::UINT64(_) => 12,
+            DataType::INT128(_) => 13,
+            DataType::UINT128(_) => 14,
+            DataType::CHAR(_) => 15,
+            DataType::NULL => 16,
+            DataType::LIST(_) => 17,
+            DataType::MAP(_) => 18,
+            DataType::TIMESTAMP(_) => 19,
+            DataType::DURATION(_) => 20,
         }
     }
 }
@@ -165,6 +454,76 @@ impl<'c> TryFrom<&'c DataType> for &'c i64 {
     }
 }
 
+impl<'c> TryFrom<&'c DataType> for &'c u8 {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT8(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c u16 {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT16(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c u32 {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT32(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c u64 {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT64(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c i128 {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::INT128(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c u128 {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT128(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c char {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::CHAR(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
 impl<'c> TryFrom<&'c DataType> for &'c f32 {
     type Error = Error;
     fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
@@ -205,6 +564,56 @@ impl<'c> TryFrom<&'c DataType> for &'c Vec<u8> {
     }
 }
 
+impl<'c> TryFrom<&'c DataType> for &'c Vec<DataType> {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::LIST(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c SystemTime {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::TIMESTAMP(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c Duration {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::DURATION(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl<'c> TryFrom<&'c DataType> for &'c BTreeMap<String, DataType> {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::MAP(inner_value) => Ok(inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for Vec<DataType> {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::LIST(inner_value) => Ok(inner_value.clone()),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
 impl TryFrom<&DataType> for bool {
     type Error = Error;
     fn try_from(value: &DataType) -> Result<Self, Self::Error> {
@@ -255,6 +664,96 @@ impl TryFrom<&DataType> for i64 {
     }
 }
 
+impl TryFrom<&DataType> for u8 {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT8(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for u16 {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT16(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for u32 {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT32(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for u64 {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT64(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for i128 {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::INT128(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for u128 {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::UINT128(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for char {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::CHAR(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for SystemTime {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::TIMESTAMP(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
+impl TryFrom<&DataType> for Duration {
+    type Error = Error;
+    fn try_from(value: &DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::DURATION(inner_value) => Ok(*inner_value),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
+
 impl TryFrom<&DataType> for f32 {
     type Error = Error;
     fn try_from(value: &DataType) -> Result<Self, Error> {
@@ -295,3 +794,25 @@ impl TryFrom<&DataType> for Vec<u8> {
     }
 }
 
+impl<'c, T: TryFrom<&'c DataType, Error = Error>> TryFrom<&'c DataType> for Option<T> {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::NULL => Ok(None),
+            _ => T::try_from(value).map(Some),
+        }
+    }
+}
+
+impl<'c, T: TryFrom<&'c DataType, Error = Error>> TryFrom<&'c DataType> for BTreeMap<String, T> {
+    type Error = Error;
+    fn try_from(value: &'c DataType) -> Result<Self, Self::Error> {
+        match value {
+            DataType::MAP(inner_value) => inner_value
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), T::try_from(value)?)))
+                .collect(),
+            _ => Err(Error::ConversionError(value.clone())),
+        }
+    }
+}
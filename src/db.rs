@@ -1,37 +1,208 @@
-use crate::*;
+use crate::{
+    datatype::timestamp_parts,
+    decoder::{decode_stream, Decoder, KeyFilter, MAX_NESTING},
+    format::{
+        DeltaHeader, Footer, Header, DELTA_FLAG, HEADERLESS_VERSION, INDEXED_FLAG, TOMBSTONE_ID,
+    },
+    journal::Journal,
+    *,
+};
+#[cfg(feature = "encryption")]
+use crate::{
+    encryption::{random_bytes, DecryptReader, EncryptWriter, NONCE_PREFIX_SIZE, SALT_SIZE},
+    format::ENCRYPTED_FLAG,
+};
 use std::{
-    collections::{hash_map::IntoIter, HashMap, VecDeque},
-    fs::File,
-    io::{Read, Write},
+    collections::{hash_map::IntoIter, HashMap, HashSet},
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
     iter::IntoIterator,
-    mem::size_of,
     path::Path,
+    sync::{Mutex, MutexGuard, PoisonError},
 };
-use yazi::{compress, decompress, CompressionLevel, Format};
-
-macro_rules! from_be_bytes {
-    ($type_name:ty, $data_buffer:ident) => {
-        <$type_name>::from_be_bytes(
-            $data_buffer
-                .drain(0..size_of::<$type_name>())
-                .collect::<Vec<u8>>()
-                .try_into()
-                .map_err(|_| Error::DecodeError)?,
-        )
-    };
+
+///Append `length` to `buffer` as an unsigned LEB128 varint
+fn write_length(buffer: &mut Vec<u8>, length: usize) {
+    write_varint(buffer, length as u64)
+}
+
+///Append `value` to `buffer` as an unsigned LEB128 varint
+fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buffer.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
 }
 
 ///Object to represent the in memory database
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, Default)]
 pub struct SmollDB {
     inner: HashMap<String, DataType>,
+    journal: Option<Journal>,
+    changes: Mutex<Changes>,
+}
+
+///Changes since the last backup, written by [`SmollDB::backup_incremental`]
+#[derive(Debug, Default)]
+struct Changes {
+    ///Checksum of the last full backup on file, the base of the incremental ones
+    base_checksum: Option<u32>,
+    ///Keys set or removed
+    dirty: HashSet<String>,
+    ///Number of incremental backups since the last full one
+    sequence: u64,
+}
+
+impl PartialEq for SmollDB {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+///Flush the directory entry of `path` to disk, so a rename inside it survives a crash
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+///Fail on files that can't be loaded on their own
+fn check_snapshot(header: &Header) -> Result<()> {
+    if header.is_encrypted() {
+        return Err(Error::Encrypted);
+    }
+    if header.is_delta() {
+        return Err(Error::Incremental);
+    }
+    Ok(())
+}
+
+///Computes the CRC32 of everything read through it
+struct ChecksumReader<R: Read> {
+    stream: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    fn new(stream: R) -> Self {
+        Self {
+            stream,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    ///Read whatever is left and return the checksum of the whole stream
+    fn finish(mut self) -> Result<u32> {
+        io::copy(&mut self, &mut io::sink())?;
+        Ok(self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.stream.read(buf)?;
+        self.hasher.update(&buf[..size]);
+        Ok(size)
+    }
+}
+
+///Computes the CRC32 of everything written through it
+struct ChecksumWriter<W: Write> {
+    stream: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn new(stream: W) -> Self {
+        Self {
+            stream,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    fn checksum(self) -> u32 {
+        self.hasher.finalize()
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = self.stream.write(buf)?;
+        self.hasher.update(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
 }
 
 impl SmollDB {
-    fn save_file(&self, filename: impl AsRef<Path>, data: &[u8]) -> Result<()> {
+    fn changes(&self) -> MutexGuard<'_, Changes> {
+        self.changes.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn changes_mut(&mut self) -> &mut Changes {
+        self.changes
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    ///Remember that `key` changed, only once a full backup started a chain of incremental backups
+    ///with [`BackupOptions::incremental_base`], so databases that never use them don't pay for it
+    fn track_change(&mut self, key: &str) {
+        let changes = self.changes_mut();
+        if changes.base_checksum.is_some() && !changes.dirty.contains(key) {
+            changes.dirty.insert(key.to_owned());
+        }
+    }
+
+    ///Track changes from a new full backup on file, with checksum `base_checksum`
+    fn start_changes(&self, base_checksum: u32) {
+        let mut changes = self.changes();
+        changes.base_checksum = Some(base_checksum);
+        changes.dirty.clear();
+        changes.sequence = 0;
+    }
+
+    ///Write `data` to a sibling temp file and rename it over the target,
+    ///so a crash never leaves a truncated database behind
+    fn save_file(
+        &self,
+        filename: impl AsRef<Path>,
+        options: &BackupOptions,
+        write: impl FnOnce(&mut BufWriter<File>) -> Result<()>,
+    ) -> Result<()> {
         let mut filename = filename.as_ref().to_owned();
         filename.set_extension("smoll");
-        File::create(filename)?.write_all(data)?;
+        let temp_filename = filename.with_extension("smoll.tmp");
+        let write_temp = || -> Result<()> {
+            let mut file = BufWriter::new(File::create(&temp_filename)?);
+            write(&mut file)?;
+            file.into_inner()
+                .map_err(|err| err.into_error())?
+                .sync_all()?;
+            Ok(())
+        };
+        if let Err(err) = write_temp() {
+            let _ = fs::remove_file(&temp_filename);
+            return Err(err);
+        }
+        if options.keep_previous && filename.exists() {
+            let backup_filename = filename.with_extension("smoll.bak");
+            fs::copy(&filename, &backup_filename)?;
+            File::open(&backup_filename)?.sync_all()?;
+        }
+        fs::rename(&temp_filename, &filename)?;
+        sync_parent(&filename)?;
         Ok(())
     }
 
@@ -43,85 +214,156 @@ impl SmollDB {
         Ok(buffer)
     }
 
-    fn encode(&self) -> Vec<u8> {
-        let mut encoded_data = Vec::<u8>::new();
-        for (key, value) in self.inner.iter() {
-            encoded_data.extend(key.len().to_be_bytes());
-            encoded_data.extend(key.as_bytes());
-            encoded_data.push(value.id());
-            match value {
-                DataType::BOOL(value) => encoded_data.push(*value as u8),
-                DataType::INT8(value) => encoded_data.extend(value.to_be_bytes()),
-                DataType::INT16(value) => encoded_data.extend(value.to_be_bytes()),
-                DataType::INT32(value) => encoded_data.extend(value.to_be_bytes()),
-                DataType::INT64(value) => encoded_data.extend(value.to_be_bytes()),
-                DataType::FLOAT32(value) => encoded_data.extend(value.to_be_bytes()),
-                DataType::FLOAT64(value) => encoded_data.extend(value.to_be_bytes()),
-                DataType::STRING(value) => {
-                    encoded_data.extend(value.len().to_be_bytes());
-                    encoded_data.extend(value.as_bytes());
-                }
-                DataType::BYTES(value) => {
-                    encoded_data.extend(value.len().to_be_bytes());
-                    encoded_data.extend(value);
-                }
+    ///Encode a single entry, a missing `value` marks `key` as removed
+    fn encode_record(
+        encoded_data: &mut Vec<u8>,
+        key: &str,
+        value: Option<&DataType>,
+    ) -> Result<()> {
+        write_length(encoded_data, key.len());
+        encoded_data.extend(key.as_bytes());
+        match value {
+            Some(value) => Self::encode_value(encoded_data, value, 0),
+            None => {
+                encoded_data.push(TOMBSTONE_ID);
+                Ok(())
             }
         }
-        encoded_data
-    }
-
-    fn decode(mut encoded_data: VecDeque<u8>) -> Result<HashMap<String, DataType>> {
-        let mut db_hashmap = HashMap::new();
-        while !encoded_data.is_empty() {
-            let key_size = from_be_bytes!(usize, encoded_data);
-            let key = String::from_utf8(encoded_data.drain(0..key_size).collect())
-                .map_err(|_| Error::DecodeError)?;
-            match encoded_data.pop_front().ok_or(Error::DecodeError)? {
-                0 => {
-                    let data = encoded_data.pop_front().ok_or(Error::DecodeError)? != 0;
-                    db_hashmap.insert(key.clone(), DataType::BOOL(data));
-                }
-                1 => {
-                    let data = from_be_bytes!(i8, encoded_data);
-                    db_hashmap.insert(key.clone(), DataType::INT8(data));
-                }
-                2 => {
-                    let data = from_be_bytes!(i16, encoded_data);
-                    db_hashmap.insert(key.clone(), DataType::INT16(data));
-                }
-                3 => {
-                    let data = from_be_bytes!(i32, encoded_data);
-                    db_hashmap.insert(key.clone(), DataType::INT32(data));
-                }
-                4 => {
-                    let data = from_be_bytes!(i64, encoded_data);
-                    db_hashmap.insert(key.clone(), DataType::INT64(data));
-                }
-                5 => {
-                    let data = from_be_bytes!(f32, encoded_data);
-                    db_hashmap.insert(key.clone(), DataType::FLOAT32(data));
-                }
-                6 => {
-                    let data = from_be_bytes!(f64, encoded_data);
-                    db_hashmap.insert(key.clone(), DataType::FLOAT64(data));
-                }
-                7 => {
-                    let data_size = from_be_bytes!(usize, encoded_data);
-                    let data = String::from_utf8(encoded_data.drain(0..data_size).collect())
-                        .map_err(|_| Error::DecodeError)?;
-                    db_hashmap.insert(key.clone(), DataType::STRING(data));
-                }
-                8 => {
-                    let size = from_be_bytes!(usize, encoded_data);
-                    let data = encoded_data.drain(0..size).collect::<Vec<u8>>();
-                    db_hashmap.insert(key.clone(), DataType::BYTES(data));
+    }
+
+    ///Encode the type tag of `value` followed by its content, `value` being nested `depth` lists
+    ///and maps deep. Values nested deeper than a file can be loaded with are refused
+    fn encode_value(encoded_data: &mut Vec<u8>, value: &DataType, depth: usize) -> Result<()> {
+        if depth == MAX_NESTING && matches!(value, DataType::LIST(_) | DataType::MAP(_)) {
+            return Err(Error::NestingTooDeep);
+        }
+        encoded_data.push(value.id());
+        match value {
+            DataType::BOOL(value) => encoded_data.push(*value as u8),
+            DataType::INT8(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::INT16(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::INT32(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::INT64(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::UINT8(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::UINT16(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::UINT32(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::UINT64(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::INT128(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::UINT128(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::CHAR(value) => encoded_data.extend(u32::from(*value).to_be_bytes()),
+            DataType::FLOAT32(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::FLOAT64(value) => encoded_data.extend(value.to_be_bytes()),
+            DataType::STRING(value) => {
+                write_length(encoded_data, value.len());
+                encoded_data.extend(value.as_bytes());
+            }
+            DataType::BYTES(value) => {
+                write_length(encoded_data, value.len());
+                encoded_data.extend(value);
+            }
+            DataType::NULL => {}
+            DataType::LIST(value) => {
+                write_length(encoded_data, value.len());
+                for value in value {
+                    Self::encode_value(encoded_data, value, depth + 1)?;
                 }
-                _ => {
-                    return Err(Error::DecodeError);
+            }
+            DataType::TIMESTAMP(value) => {
+                let (seconds, nanos) = timestamp_parts(value);
+                let seconds = i64::try_from(seconds).map_err(|_| Error::TimeOutOfRange)?;
+                encoded_data.extend(seconds.to_be_bytes());
+                encoded_data.extend(nanos.to_be_bytes());
+            }
+            DataType::DURATION(value) => {
+                encoded_data.extend(value.as_secs().to_be_bytes());
+                encoded_data.extend(value.subsec_nanos().to_be_bytes());
+            }
+            DataType::MAP(value) => {
+                write_length(encoded_data, value.len());
+                for (key, value) in value {
+                    write_length(encoded_data, key.len());
+                    encoded_data.extend(key.as_bytes());
+                    Self::encode_value(encoded_data, value, depth + 1)?;
                 }
             }
         }
-        Ok(db_hashmap)
+        Ok(())
+    }
+
+    ///Encode a single entry prefixed by its length and followed by its checksum,
+    ///`record` is used as scratch space
+    pub(crate) fn encode_framed(
+        encoded_data: &mut Vec<u8>,
+        record: &mut Vec<u8>,
+        key: &str,
+        value: Option<&DataType>,
+    ) -> Result<()> {
+        record.clear();
+        Self::encode_record(record, key, value)?;
+        write_length(encoded_data, record.len());
+        encoded_data.extend(record.iter());
+        encoded_data.extend(crc32fast::hash(record).to_be_bytes());
+        Ok(())
+    }
+
+    ///Entries sorted by key, so the same database is always encoded to the same bytes
+    fn sorted_entries(&self) -> Vec<(&String, &DataType)> {
+        let mut entries: Vec<_> = self.inner.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        entries
+    }
+
+    ///Compress the records straight into `stream`, one at a time, in key order
+    fn encode(&self, stream: &mut impl Write, options: &BackupOptions) -> Result<()> {
+        let entries = self.sorted_entries();
+        let entries = entries.into_iter().map(|(key, value)| (key, Some(value)));
+        Self::encode_entries(stream, options, entries)
+    }
+
+    ///Compress `entries` straight into `stream`, a missing value is written as a removal
+    fn encode_entries<'a>(
+        stream: &mut impl Write,
+        options: &BackupOptions,
+        entries: impl IntoIterator<Item = (&'a String, Option<&'a DataType>)>,
+    ) -> Result<()> {
+        options
+            .compression
+            .compress(options.level, stream, |stream| {
+                let mut encoded_data = Vec::<u8>::new();
+                let mut record = Vec::<u8>::new();
+                for (key, value) in entries {
+                    encoded_data.clear();
+                    Self::encode_framed(&mut encoded_data, &mut record, key, value)?;
+                    stream.write_all(&encoded_data)?;
+                }
+                Ok(())
+            })
+    }
+    ///Write the records uncompressed, ended by an empty one, then the index of their offsets and the footer
+    fn encode_indexed(&self, stream: &mut impl Write) -> Result<()> {
+        let entries = self.sorted_entries();
+        let mut index = Vec::<u8>::new();
+        write_length(&mut index, entries.len());
+        let mut encoded_data = Vec::<u8>::new();
+        let mut record = Vec::<u8>::new();
+        let mut offset = 0_u64;
+        for (key, value) in entries {
+            write_length(&mut index, key.len());
+            index.extend(key.as_bytes());
+            write_varint(&mut index, offset);
+            encoded_data.clear();
+            Self::encode_framed(&mut encoded_data, &mut record, key, Some(value))?;
+            stream.write_all(&encoded_data)?;
+            offset += encoded_data.len() as u64;
+        }
+        stream.write_all(&[0])?;
+        stream.write_all(&index)?;
+        let footer = Footer {
+            index_offset: offset + 1,
+            index_size: index.len() as u64,
+            checksum: crc32fast::hash(&index),
+        };
+        footer.write(stream)
     }
     ///Load database from file, path doesn't need the .smoll extention
     /// # Example
@@ -137,10 +379,22 @@ impl SmollDB {
     ///assert_eq!(*result, DataType::STRING(data));
     /// ```
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
-        let encoded_data = Self::read_file(path)?;
-        let (encoded_data, _) = decompress(&encoded_data, Format::Zlib)?;
-        let data = Self::decode(encoded_data.into())?;
-        Ok(Self { inner: data })
+        Self::load_with_options(path, &LoadOptions::default())
+    }
+    ///Load database from file like [`load`](crate::SmollDB::load), enforcing the limits set in `options`
+    ///
+    ///Malformed or hostile files never cause a panic, they are reported as an [`Error`](crate::Error)
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{LoadOptions, SmollDB};
+    ///let mut options = LoadOptions::new();
+    ///options.max_key_size(256).max_value_size(1 << 20).max_entries(10_000);
+    ///let database = SmollDB::load_with_options("myfile", &options).unwrap();
+    /// ```
+    pub fn load_with_options(path: impl AsRef<Path>, options: &LoadOptions) -> Result<Self> {
+        let mut filename = path.as_ref().to_owned();
+        filename.set_extension("smoll");
+        Self::load_from_stream_with_options(&mut File::open(filename)?, options)
     }
     ///Backup database on file, path doesn't need the .smoll extention
     /// # Example
@@ -156,9 +410,37 @@ impl SmollDB {
     ///assert_eq!(*result, DataType::STRING(data));
     /// ```
     pub fn backup(&self, path: impl AsRef<Path>) -> Result<()> {
-        let data = self.encode();
-        let data = compress(&data, Format::Zlib, CompressionLevel::BestSpeed)?;
-        self.save_file(path, &data)
+        self.backup_with_options(path, &BackupOptions::default())
+    }
+    ///Backup database on file like [`backup`](crate::SmollDB::backup), configured by `options`
+    ///
+    ///The file is first written next to the target, flushed to disk and then renamed over it,
+    ///so if the process dies midway the previous backup is still intact.
+    ///Records are sorted by key, so backing up equal databases with the same options
+    ///gives byte for byte identical files
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{BackupOptions, SmollDB};
+    ///let mut database = SmollDB::default();
+    ///database.set("example", String::from("data"));
+    ///database.backup_with_options("myfile", BackupOptions::new().keep_previous(true)).unwrap();
+    /// ```
+    pub fn backup_with_options(
+        &self,
+        path: impl AsRef<Path>,
+        options: &BackupOptions,
+    ) -> Result<()> {
+        let mut base_checksum = 0;
+        self.save_file(path, options, |file| {
+            let mut stream = ChecksumWriter::new(file);
+            self.backup_to_stream_with_options(&mut stream, options)?;
+            base_checksum = stream.checksum();
+            Ok(())
+        })?;
+        if options.incremental_base {
+            self.start_changes(base_checksum);
+        }
+        Ok(())
     }
     ///Load database from generic stream
     /// # Example
@@ -178,11 +460,141 @@ impl SmollDB {
     ///assert_eq!(*result, DataType::STRING(data));
     /// ```
     pub fn load_from_stream(stream: &mut impl Read) -> Result<Self> {
+        Self::load_from_stream_with_options(stream, &LoadOptions::default())
+    }
+    ///Load database from generic stream like [`load_from_stream`](crate::SmollDB::load_from_stream),
+    ///enforcing the limits set in `options`
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{LoadOptions, SmollDB};
+    ///# use std::fs::File;
+    ///let mut stream = File::open("upload.smoll").unwrap();
+    ///let mut options = LoadOptions::new();
+    ///options.max_entries(10_000);
+    ///let database = SmollDB::load_from_stream_with_options(&mut stream, &options).unwrap();
+    /// ```
+    pub fn load_from_stream_with_options(
+        stream: &mut impl Read,
+        options: &LoadOptions,
+    ) -> Result<Self> {
+        Self::load_from_stream_matching(stream, options, None)
+    }
+    ///Load only the keys accepted by `filter` from file, path doesn't need the .smoll extention
+    ///
+    ///The values of rejected keys are skipped without being decoded
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::SmollDB;
+    ///let database = SmollDB::load_filtered("myfile", |key| key.ends_with(":name")).unwrap();
+    /// ```
+    pub fn load_filtered(path: impl AsRef<Path>, filter: impl Fn(&str) -> bool) -> Result<Self> {
+        let mut filename = path.as_ref().to_owned();
+        filename.set_extension("smoll");
+        Self::load_from_stream_filtered(&mut File::open(filename)?, filter)
+    }
+    ///Load only the keys starting with `prefix` from file, like [`load_filtered`](crate::SmollDB::load_filtered)
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::SmollDB;
+    ///let users = SmollDB::load_prefix("myfile", "users:").unwrap();
+    /// ```
+    pub fn load_prefix(path: impl AsRef<Path>, prefix: &str) -> Result<Self> {
+        Self::load_filtered(path, |key| key.starts_with(prefix))
+    }
+    ///Load only the keys accepted by `filter` from generic stream,
+    ///like [`load_filtered`](crate::SmollDB::load_filtered)
+    pub fn load_from_stream_filtered(
+        stream: &mut impl Read,
+        filter: impl Fn(&str) -> bool,
+    ) -> Result<Self> {
+        Self::load_from_stream_matching(stream, &LoadOptions::default(), Some(&filter))
+    }
+
+    fn load_from_stream_matching(
+        stream: &mut impl Read,
+        options: &LoadOptions,
+        filter: Option<KeyFilter>,
+    ) -> Result<Self> {
+        let mut prefix = Vec::with_capacity(Header::SIZE);
+        stream.take(Header::SIZE as u64).read_to_end(&mut prefix)?;
+        let (header, payload) = Header::read_any(&prefix)?;
+        check_snapshot(&header)?;
+        let mut data = HashMap::new();
+        let result = decode_stream(
+            &mut payload.chain(stream),
+            header,
+            options,
+            filter,
+            &mut data,
+        );
+        match result {
+            //Headerless files have no magic to tell them apart from any other Zlib stream,
+            //data whose first record doesn't decode isn't taken for a damaged one
+            Err(Error::DecodeError { record: 0, .. }) if header.version == HEADERLESS_VERSION => {
+                return Err(Error::NotSmollFile)
+            }
+            result => result?,
+        }
+        Ok(Self {
+            inner: data,
+            ..Self::default()
+        })
+    }
+    ///Load as much as possible of a damaged database from file, path doesn't need the .smoll extention
+    ///
+    ///Every record that decodes cleanly is kept, corrupt ones are skipped up to the next record
+    ///with a valid checksum. The returned [`RecoveryReport`](crate::RecoveryReport) tells what was lost
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::SmollDB;
+    ///let (database, report) = SmollDB::load_lenient("myfile").unwrap();
+    ///if !report.is_clean() {
+    ///    println!("lost {} bytes: {:?}", report.skipped_bytes, report.errors);
+    ///}
+    /// ```
+    pub fn load_lenient(path: impl AsRef<Path>) -> Result<(Self, RecoveryReport)> {
+        let encoded_data = Self::read_file(path)?;
+        Self::load_from_stream_lenient(&mut encoded_data.as_slice())
+    }
+    ///Load as much as possible of a damaged database from generic stream,
+    ///like [`load_lenient`](crate::SmollDB::load_lenient)
+    pub fn load_from_stream_lenient(stream: &mut impl Read) -> Result<(Self, RecoveryReport)> {
         let mut encoded_data = Vec::new();
         stream.read_to_end(&mut encoded_data)?;
-        let (encoded_data, _) = decompress(&encoded_data, Format::Zlib)?;
-        let data = Self::decode(encoded_data.into())?;
-        Ok(Self { inner: data })
+        let (header, mut payload) = Header::read_any(&encoded_data)?;
+        check_snapshot(&header)?;
+        let mut report = RecoveryReport::default();
+        let mut decompressed_data = Vec::new();
+        report.truncated = header
+            .compression()?
+            .decompress(&mut payload, &mut decompressed_data)
+            .is_err();
+        let options = LoadOptions::default();
+        let mut decoder = Decoder::new(decompressed_data, header, &options);
+        let mut data = HashMap::new();
+        while !decoder.is_empty() {
+            let offset = decoder.offset();
+            match decoder.decode_record() {
+                Ok((key, value)) => {
+                    match value {
+                        Some(value) => data.insert(key, value),
+                        None => data.remove(&key),
+                    };
+                    report.recovered_records += 1;
+                }
+                Err(err) => {
+                    report.errors.push(err);
+                    report.skipped_records += 1;
+                    decoder.resync();
+                    report.skipped_bytes += decoder.offset() - offset;
+                }
+            }
+        }
+        let database = Self {
+            inner: data,
+            ..Self::default()
+        };
+        Ok((database, report))
     }
     ///Backup database onto generic stream
     /// # Example
@@ -202,11 +614,350 @@ impl SmollDB {
     ///assert_eq!(*result, DataType::STRING(data));
     /// ```
     pub fn backup_to_stream(&self, stream: &mut impl Write) -> Result<()> {
-        let data = self.encode();
-        let data = compress(&data, Format::Zlib, CompressionLevel::BestSpeed)?;
-        stream.write_all(&data)?;
+        self.backup_to_stream_with_options(stream, &BackupOptions::default())
+    }
+    ///Backup database onto generic stream like [`backup_to_stream`](crate::SmollDB::backup_to_stream),
+    ///compressed as set in `options`
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{BackupOptions, Compression, SmollDB};
+    ///let mut database = SmollDB::default();
+    ///database.set("example", vec![0_u8; 1024]);
+    ///let mut stream = Vec::new();
+    ///let mut options = BackupOptions::new();
+    ///options.compression(Compression::Zlib).level(10);
+    ///database.backup_to_stream_with_options(&mut stream, &options).unwrap();
+    ///let database = SmollDB::load_from_stream(&mut stream.as_slice()).unwrap();
+    /// ```
+    pub fn backup_to_stream_with_options(
+        &self,
+        stream: &mut impl Write,
+        options: &BackupOptions,
+    ) -> Result<()> {
+        if options.indexed {
+            let header = Header {
+                flags: Compression::None.id() | INDEXED_FLAG,
+                ..Header::default()
+            };
+            header.write(stream)?;
+            return self.encode_indexed(stream);
+        }
+        let header = Header {
+            flags: options.compression.id(),
+            ..Header::default()
+        };
+        header.write(stream)?;
+        self.encode(stream, options)
+    }
+    ///Backup database on file like [`backup`](crate::SmollDB::backup), encrypted with `key`
+    ///
+    ///Records are compressed and then sealed with ChaCha20-Poly1305, so they can't be read
+    ///or altered without the key
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{EncryptionKey, SmollDB};
+    ///let mut database = SmollDB::default();
+    ///database.set("token", String::from("secret"));
+    ///let key = EncryptionKey::from_passphrase("correct horse battery staple");
+    ///database.backup_encrypted("secrets", &key).unwrap();
+    ///let database = SmollDB::load_encrypted("secrets", &key).unwrap();
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn backup_encrypted(&self, path: impl AsRef<Path>, key: &EncryptionKey) -> Result<()> {
+        self.save_file(path, &BackupOptions::default(), |file| {
+            self.backup_to_stream_encrypted(file, key)
+        })
+    }
+    ///Load database encrypted by [`backup_encrypted`](crate::SmollDB::backup_encrypted) from file,
+    ///path doesn't need the .smoll extention
+    ///
+    ///A wrong key or an altered file fail with [`DecryptionFailed`](crate::Error::DecryptionFailed)
+    #[cfg(feature = "encryption")]
+    pub fn load_encrypted(path: impl AsRef<Path>, key: &EncryptionKey) -> Result<Self> {
+        let mut filename = path.as_ref().to_owned();
+        filename.set_extension("smoll");
+        Self::load_from_stream_encrypted(&mut File::open(filename)?, key)
+    }
+    ///Backup database onto generic stream, encrypted with `key`
+    ///like [`backup_encrypted`](crate::SmollDB::backup_encrypted)
+    #[cfg(feature = "encryption")]
+    pub fn backup_to_stream_encrypted(
+        &self,
+        stream: &mut impl Write,
+        key: &EncryptionKey,
+    ) -> Result<()> {
+        let options = BackupOptions::default();
+        let header = Header {
+            flags: options.compression.id() | ENCRYPTED_FLAG,
+            ..Header::default()
+        };
+        let mut salt = [0_u8; SALT_SIZE];
+        let mut prefix = [0_u8; NONCE_PREFIX_SIZE];
+        random_bytes(&mut salt)?;
+        random_bytes(&mut prefix)?;
+        let mut associated_data = Vec::new();
+        header.write(&mut associated_data)?;
+        associated_data.extend(salt);
+        associated_data.extend(prefix);
+        stream.write_all(&associated_data)?;
+        let mut writer = EncryptWriter::new(stream, key.cipher(&salt)?, prefix, &associated_data);
+        self.encode(&mut writer, &options)?;
+        writer.finish()
+    }
+    ///Load database encrypted with `key` from generic stream,
+    ///like [`load_encrypted`](crate::SmollDB::load_encrypted)
+    #[cfg(feature = "encryption")]
+    pub fn load_from_stream_encrypted(stream: &mut impl Read, key: &EncryptionKey) -> Result<Self> {
+        let header = Header::read(stream)?;
+        if !header.is_encrypted() {
+            return Err(Error::DecryptionFailed);
+        }
+        let mut salt = [0_u8; SALT_SIZE];
+        let mut prefix = [0_u8; NONCE_PREFIX_SIZE];
+        stream
+            .read_exact(&mut salt)
+            .and_then(|_| stream.read_exact(&mut prefix))
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::UnexpectedEof => Error::DecryptionFailed,
+                _ => Error::FileError(err),
+            })?;
+        let mut associated_data = Vec::new();
+        header.write(&mut associated_data)?;
+        associated_data.extend(salt);
+        associated_data.extend(prefix);
+        let mut reader = DecryptReader::new(stream, key.cipher(&salt)?, prefix, &associated_data);
+        let mut data = HashMap::new();
+        let result = decode_stream(
+            &mut reader,
+            header,
+            &LoadOptions::default(),
+            None,
+            &mut data,
+        );
+        if reader.failed {
+            return Err(Error::DecryptionFailed);
+        }
+        result?;
+        Ok(Self {
+            inner: data,
+            ..Self::default()
+        })
+    }
+    ///Map an indexed database in memory read-only, path doesn't need the .smoll extention
+    ///
+    ///The file must be backed up with [`BackupOptions::indexed`](crate::BackupOptions::indexed).
+    ///Strings and bytes are returned as views into the mapped file instead of being copied
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{BackupOptions, DataRef, SmollDB};
+    ///let mut database = SmollDB::default();
+    ///database.set("example", String::from("data"));
+    ///database.backup_with_options("myfile", BackupOptions::new().indexed(true)).unwrap();
+    ///let view = SmollDB::mmap("myfile").unwrap();
+    ///assert_eq!(view.get("example").unwrap(), Some(DataRef::STRING("data")));
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub fn mmap(path: impl AsRef<Path>) -> Result<SmollView> {
+        SmollView::open(path)
+    }
+    ///Backup only the keys set or removed since the last backup to `delta_path`,
+    ///path doesn't need the .smoll extention
+    ///
+    ///The chain starts from a full backup made with [`BackupOptions::incremental_base`],
+    ///changes are only tracked from there. `base_path` must be that file, untouched since, or this fails
+    ///with [`BaseMismatch`](crate::Error::BaseMismatch).
+    ///Use [`load_incremental`](crate::SmollDB::load_incremental) to rebuild the database
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{BackupOptions, SmollDB};
+    ///let mut database = SmollDB::default();
+    ///database.set("example", String::from("data"));
+    ///database.backup_with_options("base", BackupOptions::new().incremental_base(true)).unwrap();
+    ///database.set("other", String::from("data"));
+    ///database.backup_incremental("base", "base_1").unwrap();
+    ///database.remove(&"example");
+    ///database.backup_incremental("base", "base_2").unwrap();
+    ///let copy = SmollDB::load_incremental("base", &["base_1", "base_2"]).unwrap();
+    ///assert_eq!(database, copy);
+    /// ```
+    pub fn backup_incremental(
+        &self,
+        base_path: impl AsRef<Path>,
+        delta_path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let mut base = base_path.as_ref().to_owned();
+        base.set_extension("smoll");
+        let base_checksum = ChecksumReader::new(File::open(base)?).finish()?;
+        let mut changes = self.changes();
+        if changes.base_checksum != Some(base_checksum) {
+            return Err(Error::BaseMismatch);
+        }
+        let delta = DeltaHeader {
+            base_checksum,
+            sequence: changes.sequence + 1,
+        };
+        let options = BackupOptions::default();
+        let mut dirty: Vec<_> = changes.dirty.iter().collect();
+        dirty.sort_unstable();
+        self.save_file(delta_path, &options, |file| {
+            let header = Header {
+                flags: options.compression.id() | DELTA_FLAG,
+                ..Header::default()
+            };
+            header.write(file)?;
+            delta.write(file)?;
+            let entries = dirty.into_iter().map(|key| (key, self.inner.get(key)));
+            Self::encode_entries(file, &options, entries)
+        })?;
+        changes.dirty.clear();
+        changes.sequence = delta.sequence;
         Ok(())
     }
+    ///Load a full backup and apply the chain of incremental backups made on top of it, in order,
+    ///paths don't need the .smoll extention
+    ///
+    ///Fails with [`BrokenDeltaChain`](crate::Error::BrokenDeltaChain) if an incremental backup
+    ///belongs to another base or is out of order. Further incremental backups of the returned
+    ///database continue the chain
+    pub fn load_incremental(
+        base_path: impl AsRef<Path>,
+        delta_paths: &[impl AsRef<Path>],
+    ) -> Result<Self> {
+        let mut base = base_path.as_ref().to_owned();
+        base.set_extension("smoll");
+        let mut stream = ChecksumReader::new(File::open(base)?);
+        let mut database = Self::load_from_stream(&mut stream)?;
+        let base_checksum = stream.finish()?;
+        let options = LoadOptions::default();
+        for (position, delta_path) in delta_paths.iter().enumerate() {
+            let mut filename = delta_path.as_ref().to_owned();
+            filename.set_extension("smoll");
+            let mut stream = File::open(filename)?;
+            let header = Header::read(&mut stream)?;
+            if !header.is_delta() {
+                return Err(Error::BrokenDeltaChain(position));
+            }
+            let delta = DeltaHeader::read(&mut stream)?;
+            if delta.base_checksum != base_checksum || delta.sequence != position as u64 + 1 {
+                return Err(Error::BrokenDeltaChain(position));
+            }
+            decode_stream(&mut stream, header, &options, None, &mut database.inner)?;
+        }
+        let changes = database.changes_mut();
+        changes.base_checksum = Some(base_checksum);
+        changes.sequence = delta_paths.len() as u64;
+        Ok(database)
+    }
+    ///Format version of the file, path doesn't need the .smoll extention
+    ///
+    ///Files written before the format had a header are reported as version 0,
+    ///files newer than this crate with their own version. Files older than [`FORMAT_VERSION`](crate::FORMAT_VERSION) can be upgraded
+    ///with [`migrate`](crate::SmollDB::migrate)
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{SmollDB, FORMAT_VERSION};
+    ///if SmollDB::format_version("myfile").unwrap() < FORMAT_VERSION {
+    ///    SmollDB::migrate("myfile", "myfile").unwrap();
+    ///}
+    /// ```
+    pub fn format_version(path: impl AsRef<Path>) -> Result<u8> {
+        let mut filename = path.as_ref().to_owned();
+        filename.set_extension("smoll");
+        Self::format_version_from_stream(&mut File::open(filename)?)
+    }
+    ///Format version of the database at the start of generic stream, only the header is read
+    pub fn format_version_from_stream(stream: &mut impl Read) -> Result<u8> {
+        let mut encoded_data = Vec::with_capacity(Header::SIZE);
+        stream
+            .take(Header::SIZE as u64)
+            .read_to_end(&mut encoded_data)?;
+        Header::read_version(&encoded_data)
+    }
+    ///Rewrite the database in `src` in the latest format to `dst`, paths don't need the .smoll extention
+    ///
+    ///Any format version this crate ever wrote is understood, including files without a header.
+    ///The compression and the index of `src` are kept, `src` and `dst` can be the same file.
+    ///Encrypted and incremental backups can't be migrated
+    pub fn migrate(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+        let encoded_data = Self::read_file(src)?;
+        let (header, mut payload) = Header::read_any(&encoded_data)?;
+        check_snapshot(&header)?;
+        let mut database = Self::default();
+        decode_stream(
+            &mut payload,
+            header,
+            &LoadOptions::default(),
+            None,
+            &mut database.inner,
+        )?;
+        let mut options = BackupOptions::new();
+        options
+            .compression(header.compression()?)
+            .indexed(header.is_indexed());
+        database.backup_with_options(dst, &options)
+    }
+    ///Open a journaled database, path doesn't need the .smoll extention
+    ///
+    ///The last snapshot saved at `path` is loaded, if any, and the log of mutations stored next to it
+    ///as `<path>.smoll.log` is replayed on top of it. From then on every [`set`](crate::SmollDB::set) and
+    ///[`remove`](crate::SmollDB::remove) is appended to the log, use [`compact`](crate::SmollDB::compact)
+    ///to fold the log back into a fresh snapshot
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{DataType, SmollDB};
+    ///let mut database = SmollDB::open("myfile").unwrap();
+    ///let data = String::from("data");
+    ///let key = String::from("example");
+    ///database.set(key.clone(), data.clone());
+    ///database.sync().unwrap();
+    ///let database = SmollDB::open("myfile").unwrap();
+    ///let result = database.get(&key).unwrap();
+    ///assert_eq!(*result, DataType::STRING(data));
+    /// ```
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut snapshot = path.as_ref().to_owned();
+        snapshot.set_extension("smoll");
+        let mut inner = if snapshot.exists() {
+            Self::load(&snapshot)?.inner
+        } else {
+            HashMap::new()
+        };
+        let journal = Journal::open(&snapshot, &mut inner)?;
+        Ok(Self {
+            inner,
+            journal: Some(journal),
+            ..Self::default()
+        })
+    }
+    ///Flush the log of a journaled database to disk, does nothing for other databases
+    ///
+    ///Also reports the error of any append to the log that failed since the last [`compact`](crate::SmollDB::compact)
+    pub fn sync(&self) -> Result<()> {
+        match &self.journal {
+            Some(journal) => journal.sync(),
+            None => Ok(()),
+        }
+    }
+    ///Fold the log of a journaled database into a fresh snapshot and empty the log,
+    ///does nothing for other databases
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{DataType, SmollDB};
+    ///let mut database = SmollDB::open("myfile").unwrap();
+    ///database.set("example", String::from("data"));
+    ///database.compact().unwrap();
+    ///let database = SmollDB::load("myfile").unwrap();
+    ///assert!(database.contains_key(&"example"));
+    /// ```
+    pub fn compact(&mut self) -> Result<()> {
+        if let Some(journal) = &self.journal {
+            self.backup(journal.snapshot())?;
+        }
+        match &mut self.journal {
+            Some(journal) => journal.compact(),
+            None => Ok(()),
+        }
+    }
     ///Save `value` in the database with the specified `key`
     /// # Example
     /// ```no_run
@@ -220,7 +971,12 @@ impl SmollDB {
     /// ```
     #[inline]
     pub fn set(&mut self, key: impl ToString, value: impl Into<DataType>) -> Option<DataType> {
-        self.inner.insert(key.to_string(), value.into())
+        let (key, value) = (key.to_string(), value.into());
+        if let Some(journal) = &mut self.journal {
+            journal.append(&key, Some(&value));
+        }
+        self.track_change(&key);
+        self.inner.insert(key, value)
     }
     ///Get `value` with the specified `key`, return a empty optional if `key` is not contained in the database
     /// # Example
@@ -237,6 +993,29 @@ impl SmollDB {
     pub fn get(&self, key: &impl ToString) -> Option<&DataType> {
         self.inner.get(&key.to_string())
     }
+    ///Get the value nested in the value of `key` by following `path`, return a empty optional if any step is missing
+    ///
+    ///Every step of the path is a key of a [`MAP`](crate::DataType::MAP),
+    ///or the position of an element of a [`LIST`](crate::DataType::LIST)
+    /// # Example
+    /// ```no_run
+    ///# use smolldb::{DataType, SmollDB};
+    ///# use std::collections::BTreeMap;
+    ///let mut database = SmollDB::default();
+    ///let address = BTreeMap::from([(String::from("city"), String::from("Rome"))]);
+    ///let user = BTreeMap::from([(String::from("address"), DataType::from(address))]);
+    ///database.set("user:1", user);
+    ///let result = database.get_path(&"user:1", &["address", "city"]).unwrap();
+    ///assert_eq!(*result, DataType::STRING(String::from("Rome")));
+    /// ```
+    pub fn get_path(&self, key: &impl ToString, path: &[impl AsRef<str>]) -> Option<&DataType> {
+        path.iter()
+            .try_fold(self.get(key)?, |value, step| match value {
+                DataType::MAP(map) => map.get(step.as_ref()),
+                DataType::LIST(list) => list.get(step.as_ref().parse::<usize>().ok()?),
+                _ => None,
+            })
+    }
     ///Check if database contains the specified key
     ///  # Example
     /// ```no_run
@@ -265,7 +1044,15 @@ impl SmollDB {
     ///assert!(!database.contains_key(&key));
     /// ```
     pub fn remove(&mut self, key: &impl ToString) -> Option<DataType> {
-        self.inner.remove(&key.to_string())
+        let key = key.to_string();
+        let value = self.inner.remove(&key);
+        if let (Some(journal), Some(_)) = (&mut self.journal, &value) {
+            journal.append(&key, None);
+        }
+        if value.is_some() {
+            self.track_change(&key);
+        }
+        value
     }
     ///Similar to [`get`](crate::SmollDB::get) but converts the [`DataType`](crate::DataType) to the specified type if possible.
     ///Returns `None` if the key is not contained in the database.
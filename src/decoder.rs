@@ -0,0 +1,484 @@
+use crate::{
+    datatype::timestamp_from_parts,
+    format::{Header, CHECKSUM_VERSION, LEGACY_VERSION, TOMBSTONE_ID},
+    DataType, DecodeReason, Error, LoadOptions, Result,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, ErrorKind, Read, Write},
+    mem::size_of,
+    time::Duration,
+};
+
+macro_rules! from_be_bytes {
+    ($type_name:ty, $decoder:ident) => {
+        <$type_name>::from_be_bytes($decoder.read_array::<{ size_of::<$type_name>() }>()?)
+    };
+}
+
+///Lists and maps nested deeper than this are rejected, so hostile data can't exhaust the stack
+pub(crate) const MAX_NESTING: usize = 64;
+
+///Predicate choosing which keys to load
+pub(crate) type KeyFilter<'a> = &'a dyn Fn(&str) -> bool;
+
+///Decoder for the decompressed records of a .smoll file
+///
+///Reads fields through a cursor over the buffered data without copying them,
+///and keeps track of where it is so failures can be reported
+///with the byte offset, the index of the record and its key
+pub(crate) struct Decoder<'a> {
+    encoded_data: Vec<u8>,
+    position: usize,
+    ///Bytes already decoded and dropped from the front of `encoded_data`
+    discarded: u64,
+    version: u8,
+    ///Records of indexed files end with an empty one, followed by the index
+    indexed: bool,
+    ended: bool,
+    filter: Option<KeyFilter<'a>>,
+    options: &'a LoadOptions,
+    record: usize,
+    key: Option<String>,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(encoded_data: Vec<u8>, header: Header, options: &'a LoadOptions) -> Self {
+        Self {
+            encoded_data,
+            position: 0,
+            discarded: 0,
+            version: header.version,
+            indexed: header.is_indexed(),
+            ended: false,
+            filter: None,
+            options,
+            record: 0,
+            key: None,
+        }
+    }
+
+    ///Number of bytes decoded so far
+    pub(crate) fn offset(&self) -> u64 {
+        self.discarded + self.position as u64
+    }
+
+    ///Data left to decode
+    fn remaining(&self) -> &[u8] {
+        &self.encoded_data[self.position..]
+    }
+
+    ///Append more data to decode, for decoding a stream piece by piece.
+    ///Data already decoded is dropped first, so the buffer only holds the unfinished record
+    pub(crate) fn push(&mut self, encoded_data: &[u8]) {
+        if self.ended {
+            return;
+        }
+        self.encoded_data.drain(..self.position);
+        self.discarded += self.position as u64;
+        self.position = 0;
+        self.encoded_data.extend_from_slice(encoded_data);
+    }
+
+    ///Check if the next record is entirely buffered, or is broken or large enough to fail right away.
+    ///Formats without framing can only be decoded once all the data is pushed
+    fn has_record(&mut self) -> bool {
+        if self.version < CHECKSUM_VERSION || self.is_empty() {
+            return false;
+        }
+        let encoded_data = self.remaining();
+        match read_varint(encoded_data) {
+            Some((record_size, _)) if self.options.check_record_size(record_size).is_err() => true,
+            Some((record_size, length_size)) => {
+                (encoded_data.len() - length_size) as u64
+                    >= record_size.saturating_add(size_of::<u32>() as u64)
+            }
+            None => encoded_data.len() >= MAX_VARINT_SIZE,
+        }
+    }
+
+    ///Check if there are no records left, in indexed files this stops before the index
+    pub(crate) fn is_empty(&mut self) -> bool {
+        if self.indexed && self.remaining().first() == Some(&0) {
+            self.ended = true;
+        }
+        self.ended || self.position == self.encoded_data.len()
+    }
+
+    fn error(&self, reason: DecodeReason) -> Error {
+        self.error_at(self.offset(), reason)
+    }
+
+    fn error_at(&self, offset: u64, reason: DecodeReason) -> Error {
+        Error::DecodeError {
+            offset,
+            record: self.record,
+            key: self.key.clone(),
+            reason,
+        }
+    }
+
+    ///Advance past the next `size` bytes and return them, failing if there are not enough of them
+    fn take(&mut self, size: usize) -> Result<&[u8]> {
+        if size > self.remaining().len() {
+            return Err(self.error(DecodeReason::UnexpectedEof));
+        }
+        let start = self.position;
+        self.position += size;
+        Ok(&self.encoded_data[start..self.position])
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    ///Read a LEB128 varint length, or a fixed 8 byte length for legacy and headerless files
+    fn read_length(&mut self) -> Result<usize> {
+        let offset = self.offset();
+        let length = if self.version <= LEGACY_VERSION {
+            from_be_bytes!(u64, self)
+        } else {
+            let mut length = 0_u64;
+            let mut shift = 0;
+            loop {
+                let byte = self.read_u8()?;
+                if shift == 63 && byte > 1 {
+                    return Err(self.error_at(offset, DecodeReason::LengthOverflow));
+                }
+                length |= u64::from(byte & 0x7F) << shift;
+                if byte & 0x80 == 0 {
+                    break length;
+                }
+                shift += 7;
+            }
+        };
+        usize::try_from(length).map_err(|_| self.error_at(offset, DecodeReason::LengthOverflow))
+    }
+
+    fn read_string(&mut self, size: usize) -> Result<String> {
+        let offset = self.offset();
+        std::str::from_utf8(self.take(size)?)
+            .map(str::to_owned)
+            .map_err(|_| self.error_at(offset, DecodeReason::InvalidUtf8))
+    }
+
+    ///Decode a key and its value, a missing value marks the key as removed
+    fn decode_entry(&mut self) -> Result<(String, Option<DataType>)> {
+        let key_size = self.read_length()?;
+        self.options.check_key_size(key_size)?;
+        let key = self.read_string(key_size)?;
+        self.key = Some(key.clone());
+        let offset = self.offset();
+        match self.read_u8()? {
+            TOMBSTONE_ID => Ok((key, None)),
+            tag => Ok((key, Some(self.decode_value(tag, offset, 0)?))),
+        }
+    }
+
+    ///Decode a value of type `tag`, starting at `offset`, nested in `depth` lists
+    fn decode_value(&mut self, tag: u8, offset: u64, depth: usize) -> Result<DataType> {
+        let value = match tag {
+            0 => DataType::BOOL(self.read_u8()? != 0),
+            1 => DataType::INT8(from_be_bytes!(i8, self)),
+            2 => DataType::INT16(from_be_bytes!(i16, self)),
+            3 => DataType::INT32(from_be_bytes!(i32, self)),
+            4 => DataType::INT64(from_be_bytes!(i64, self)),
+            5 => DataType::FLOAT32(from_be_bytes!(f32, self)),
+            6 => DataType::FLOAT64(from_be_bytes!(f64, self)),
+            7 => {
+                let data_size = self.read_length()?;
+                self.options.check_value_size(data_size)?;
+                DataType::STRING(self.read_string(data_size)?)
+            }
+            8 => {
+                let size = self.read_length()?;
+                self.options.check_value_size(size)?;
+                DataType::BYTES(self.take(size)?.to_vec())
+            }
+            9 => DataType::UINT8(from_be_bytes!(u8, self)),
+            10 => DataType::UINT16(from_be_bytes!(u16, self)),
+            11 => DataType::UINT32(from_be_bytes!(u32, self)),
+            12 => DataType::UINT64(from_be_bytes!(u64, self)),
+            13 => DataType::INT128(from_be_bytes!(i128, self)),
+            14 => DataType::UINT128(from_be_bytes!(u128, self)),
+            15 => {
+                let value = from_be_bytes!(u32, self);
+                let value = char::from_u32(value)
+                    .ok_or_else(|| self.error_at(offset, DecodeReason::InvalidChar(value)))?;
+                DataType::CHAR(value)
+            }
+            16 => DataType::NULL,
+            17 => {
+                if depth == MAX_NESTING {
+                    return Err(self.error_at(offset, DecodeReason::NestingTooDeep));
+                }
+                let size = self.read_length()?;
+                //Every element takes at least a byte, so there can't be more of them than bytes
+                self.options.check_value_size(size)?;
+                let start = offset;
+                let mut list = Vec::with_capacity(size.min(self.remaining().len()));
+                for _ in 0..size {
+                    let offset = self.offset();
+                    let tag = self.read_u8()?;
+                    list.push(self.decode_value(tag, offset, depth + 1)?);
+                    self.check_nested_size(start)?;
+                }
+                DataType::LIST(list)
+            }
+            18 => {
+                if depth == MAX_NESTING {
+                    return Err(self.error_at(offset, DecodeReason::NestingTooDeep));
+                }
+                let size = self.read_length()?;
+                self.options.check_value_size(size)?;
+                let start = offset;
+                let mut map = BTreeMap::new();
+                for _ in 0..size {
+                    let key_size = self.read_length()?;
+                    self.options.check_key_size(key_size)?;
+                    let key = self.read_string(key_size)?;
+                    let offset = self.offset();
+                    let tag = self.read_u8()?;
+                    map.insert(key, self.decode_value(tag, offset, depth + 1)?);
+                    self.check_nested_size(start)?;
+                }
+                DataType::MAP(map)
+            }
+            19 => {
+                let seconds = from_be_bytes!(i64, self);
+                let nanos = from_be_bytes!(u32, self);
+                let value = timestamp_from_parts(seconds, nanos)
+                    .ok_or_else(|| self.error_at(offset, DecodeReason::InvalidTime))?;
+                DataType::TIMESTAMP(value)
+            }
+            20 => {
+                let seconds = from_be_bytes!(u64, self);
+                let nanos = from_be_bytes!(u32, self);
+                if nanos >= 1_000_000_000 {
+                    return Err(self.error_at(offset, DecodeReason::InvalidTime));
+                }
+                DataType::DURATION(Duration::new(seconds, nanos))
+            }
+            id => {
+                return Err(self.error_at(offset, DecodeReason::UnknownTypeTag(id)));
+            }
+        };
+        Ok(value)
+    }
+
+    ///Check the size of the list or map starting at `start` decoded so far, lists and maps
+    ///count as a whole against the value size limit
+    fn check_nested_size(&self, start: u64) -> Result<()> {
+        self.options
+            .check_value_size((self.offset() - start) as usize)
+    }
+
+    ///Read the length of the next framed record and check its checksum, returning the offset
+    ///of the record and its length. The record itself is left to decode
+    fn check_record(&mut self) -> Result<(u64, usize)> {
+        let offset = self.offset();
+        let record_size = self.read_length()?;
+        self.options.check_record_size(record_size as u64)?;
+        if record_size.saturating_add(size_of::<u32>()) > self.remaining().len() {
+            return Err(self.error(DecodeReason::UnexpectedEof));
+        }
+        let (record, checksum) = self.remaining().split_at(record_size);
+        let checksum = u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]);
+        if crc32fast::hash(record) != checksum {
+            return Err(Error::ChecksumMismatch {
+                record: self.record,
+                offset,
+            });
+        }
+        Ok((offset, record_size))
+    }
+
+    ///Decode the next record, checking its length and checksum on framed formats
+    pub(crate) fn decode_record(&mut self) -> Result<(String, Option<DataType>)> {
+        self.key = None;
+        self.options.check_entries(self.record + 1)?;
+        if self.version < CHECKSUM_VERSION {
+            let entry = self.decode_entry()?;
+            self.record += 1;
+            return Ok(entry);
+        }
+        let (offset, record_size) = self.check_record()?;
+        let record_offset = self.offset();
+        let entry = self.decode_entry()?;
+        if self.offset() - record_offset != record_size as u64 {
+            return Err(self.error_at(offset, DecodeReason::RecordSizeMismatch));
+        }
+        self.take(size_of::<u32>())?;
+        self.record += 1;
+        Ok(entry)
+    }
+
+    ///Key of the next framed record, read in place without decoding the record
+    fn peek_key(&self) -> Option<&str> {
+        let (_, length_size) = read_varint(self.remaining())?;
+        let record = &self.remaining()[length_size..];
+        let (key_size, length_size) = read_varint(record)?;
+        let key = record[length_size..].get(..usize::try_from(key_size).ok()?)?;
+        std::str::from_utf8(key).ok()
+    }
+
+    ///Decode the next record like [`decode_record`](Self::decode_record), or return `None`
+    ///if the filter rejects its key. On framed formats rejected records are only checked
+    ///against their checksum and skipped, their values are never decoded
+    pub(crate) fn decode_filtered(&mut self) -> Result<Option<(String, Option<DataType>)>> {
+        let filter = match self.filter {
+            Some(filter) => filter,
+            None => return self.decode_record().map(Some),
+        };
+        if self.version < CHECKSUM_VERSION {
+            let entry = self.decode_record()?;
+            return Ok(Some(entry).filter(|(key, _)| filter(key)));
+        }
+        match self.peek_key() {
+            Some(key) if !filter(key) => {
+                self.key = None;
+                self.options.check_entries(self.record + 1)?;
+                let (_, record_size) = self.check_record()?;
+                self.position += record_size + size_of::<u32>();
+                self.record += 1;
+                Ok(None)
+            }
+            _ => self.decode_record().map(Some),
+        }
+    }
+
+    ///Decode the index of an indexed file, mapping every key to the offset of its record
+    pub(crate) fn decode_index(mut self) -> Result<BTreeMap<String, u64>> {
+        let entries = self.read_length()?;
+        let mut index = BTreeMap::new();
+        for _ in 0..entries {
+            let key_size = self.read_length()?;
+            self.options.check_key_size(key_size)?;
+            let key = self.read_string(key_size)?;
+            let offset = self.read_length()?;
+            index.insert(key, offset as u64);
+        }
+        Ok(index)
+    }
+
+    ///Skip forward to the next record with a valid length and checksum, after a failed
+    ///[`decode_record`](Self::decode_record). Formats without checksums can't be resynchronized,
+    ///so for them everything left is skipped
+    pub(crate) fn resync(&mut self) {
+        self.key = None;
+        self.record += 1;
+        while self.position < self.encoded_data.len() {
+            if self.version >= CHECKSUM_VERSION && is_valid_record(self.remaining()) {
+                return;
+            }
+            self.position += 1;
+        }
+    }
+
+    ///Decode the next record into `db_hashmap`, unless the filter rejects it
+    fn decode_into_map(&mut self, db_hashmap: &mut HashMap<String, DataType>) -> Result<()> {
+        match self.decode_filtered()? {
+            Some((key, Some(value))) => db_hashmap.insert(key, value),
+            Some((key, None)) => db_hashmap.remove(&key),
+            None => None,
+        };
+        Ok(())
+    }
+
+    ///Decode every remaining record into `db_hashmap`
+    fn decode_into(&mut self, db_hashmap: &mut HashMap<String, DataType>) -> Result<()> {
+        while !self.is_empty() {
+            self.decode_into_map(db_hashmap)?;
+        }
+        Ok(())
+    }
+}
+
+///Receives the decompressed data and decodes each record as soon as it is complete
+struct RecordSink<'a, 'm> {
+    decoder: Decoder<'a>,
+    db_hashmap: &'m mut HashMap<String, DataType>,
+    error: Option<Error>,
+}
+
+impl Write for RecordSink<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.decoder.push(buf);
+        while self.decoder.has_record() {
+            if let Err(err) = self.decoder.decode_into_map(self.db_hashmap) {
+                self.error = Some(err);
+                return Err(io::Error::from(ErrorKind::InvalidData));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+///Decompress and decode `stream` a piece at a time into `db_hashmap`, so that beside the map
+///only the records being decoded are kept in memory
+pub(crate) fn decode_stream(
+    stream: &mut impl Read,
+    header: Header,
+    options: &LoadOptions,
+    filter: Option<KeyFilter>,
+    db_hashmap: &mut HashMap<String, DataType>,
+) -> Result<()> {
+    let mut decoder = Decoder::new(Vec::new(), header, options);
+    decoder.filter = filter;
+    let mut sink = RecordSink {
+        decoder,
+        db_hashmap,
+        error: None,
+    };
+    let result = header.compression()?.decompress(stream, &mut sink);
+    if let Some(err) = sink.error.take() {
+        return Err(err);
+    }
+    result?;
+    sink.decoder.decode_into(sink.db_hashmap)
+}
+
+///Longest LEB128 encoding of a `u64`
+pub(crate) const MAX_VARINT_SIZE: usize = 10;
+
+///Parse the LEB128 varint at the start of `encoded_data`, returning its value and size.
+///Returns `None` if it is incomplete or too long
+pub(crate) fn read_varint(encoded_data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0_u64;
+    for (position, byte) in encoded_data.iter().take(MAX_VARINT_SIZE).enumerate() {
+        value |= u64::from(byte & 0x7F) << (7 * position);
+        if byte & 0x80 == 0 {
+            return Some((value, position + 1));
+        }
+    }
+    None
+}
+
+///Check if `encoded_data` starts with a record whose checksum matches its content
+pub(crate) fn is_valid_record(encoded_data: &[u8]) -> bool {
+    let (record_size, length_size) = match read_varint(encoded_data) {
+        Some(length) => length,
+        None => return false,
+    };
+    let record_size = match usize::try_from(record_size) {
+        Ok(record_size) if record_size >= 2 => record_size,
+        _ => return false,
+    };
+    let encoded_data = &encoded_data[length_size..];
+    if record_size.saturating_add(size_of::<u32>()) > encoded_data.len() {
+        return false;
+    }
+    let (record, checksum) = encoded_data.split_at(record_size);
+    crc32fast::hash(record)
+        == u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]])
+}
@@ -0,0 +1,238 @@
+use crate::{Error, Result};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    ChaCha20Poly1305, KeyInit, Nonce,
+};
+use std::{
+    fmt::Debug,
+    io::{self, ErrorKind, Read, Write},
+};
+
+///Size of the random salt stored after the header of encrypted files
+pub(crate) const SALT_SIZE: usize = 16;
+///Size of the random nonce prefix stored after the salt
+pub(crate) const NONCE_PREFIX_SIZE: usize = 7;
+///Plaintext bytes sealed together, every chunk is followed by its authentication tag
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+
+///Key used by [`SmollDB::backup_encrypted`](crate::SmollDB::backup_encrypted)
+///and [`SmollDB::load_encrypted`](crate::SmollDB::load_encrypted)
+///
+///Either 32 raw bytes, or a passphrase stretched with Argon2id and the random salt stored in every file
+/// # Example
+/// ```no_run
+///# use smolldb::{EncryptionKey, SmollDB};
+///let key = EncryptionKey::from_passphrase("correct horse battery staple");
+///let database = SmollDB::load_encrypted("secrets", &key).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct EncryptionKey {
+    secret: Secret,
+}
+
+#[derive(Clone)]
+enum Secret {
+    Raw([u8; 32]),
+    Passphrase(Vec<u8>),
+}
+
+impl EncryptionKey {
+    ///Use `key` as it is, it must come from a secure random source
+    pub fn from_bytes(key: [u8; 32]) -> Self {
+        Self {
+            secret: Secret::Raw(key),
+        }
+    }
+    ///Derive the key from `passphrase`, a new key is derived for every file from its own salt
+    pub fn from_passphrase(passphrase: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: Secret::Passphrase(passphrase.as_ref().to_vec()),
+        }
+    }
+
+    ///Build the cipher for a file with the given `salt`
+    pub(crate) fn cipher(&self, salt: &[u8; SALT_SIZE]) -> Result<ChaCha20Poly1305> {
+        let mut key = [0_u8; 32];
+        match &self.secret {
+            Secret::Raw(raw) => key = *raw,
+            Secret::Passphrase(passphrase) => Argon2::default()
+                .hash_password_into(passphrase, salt, &mut key)
+                .map_err(|_| Error::DecryptionFailed)?,
+        }
+        Ok(ChaCha20Poly1305::new(&key.into()))
+    }
+}
+
+impl Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey { .. }")
+    }
+}
+
+///Fill `buffer` with random bytes from the operating system
+pub(crate) fn random_bytes(buffer: &mut [u8]) -> Result<()> {
+    getrandom::getrandom(buffer).map_err(|err| Error::FileError(err.into()))
+}
+
+///Nonce of the chunk at `index`, the last chunk is marked so a truncated file can't pass as whole
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_SIZE], index: u32, last: bool) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[..NONCE_PREFIX_SIZE].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_SIZE..NONCE_PREFIX_SIZE + 4].copy_from_slice(&index.to_be_bytes());
+    nonce[NONCE_PREFIX_SIZE + 4] = last as u8;
+    nonce
+}
+
+///Seals everything written to it in chunks, [`finish`](Self::finish) must be called to write the last one
+pub(crate) struct EncryptWriter<'a, W: Write> {
+    stream: W,
+    cipher: ChaCha20Poly1305,
+    prefix: [u8; NONCE_PREFIX_SIZE],
+    ///Authenticated along every chunk, binds the header to the data
+    associated_data: &'a [u8],
+    index: u32,
+    buffer: Vec<u8>,
+}
+
+impl<'a, W: Write> EncryptWriter<'a, W> {
+    pub(crate) fn new(
+        stream: W,
+        cipher: ChaCha20Poly1305,
+        prefix: [u8; NONCE_PREFIX_SIZE],
+        associated_data: &'a [u8],
+    ) -> Self {
+        Self {
+            stream,
+            cipher,
+            prefix,
+            associated_data,
+            index: 0,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> io::Result<()> {
+        let nonce = chunk_nonce(&self.prefix, self.index, last);
+        let payload = Payload {
+            msg: &self.buffer,
+            aad: self.associated_data,
+        };
+        let sealed = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| io::Error::new(ErrorKind::Other, "encryption failed"))?;
+        self.stream.write_all(&sealed)?;
+        self.buffer.clear();
+        self.index = self
+            .index
+            .checked_add(1)
+            .ok_or_else(|| io::Error::new(ErrorKind::Other, "too many chunks"))?;
+        Ok(())
+    }
+
+    ///Seal the remaining data as the last chunk. A full buffer is sealed as a regular chunk
+    ///followed by an empty last one, since the reader takes every full chunk for a regular one
+    pub(crate) fn finish(mut self) -> Result<()> {
+        if self.buffer.len() == CHUNK_SIZE {
+            self.seal_chunk(false)?;
+        }
+        self.seal_chunk(true)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() == CHUNK_SIZE {
+            self.seal_chunk(false)?;
+        }
+        let size = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+///Opens the chunks written by [`EncryptWriter`], failing on any that was altered, reordered or cut off
+pub(crate) struct DecryptReader<'a, R: Read> {
+    stream: R,
+    cipher: ChaCha20Poly1305,
+    prefix: [u8; NONCE_PREFIX_SIZE],
+    associated_data: &'a [u8],
+    index: u32,
+    buffer: Vec<u8>,
+    position: usize,
+    finished: bool,
+    ///Set once a chunk fails to authenticate
+    pub(crate) failed: bool,
+}
+
+impl<'a, R: Read> DecryptReader<'a, R> {
+    pub(crate) fn new(
+        stream: R,
+        cipher: ChaCha20Poly1305,
+        prefix: [u8; NONCE_PREFIX_SIZE],
+        associated_data: &'a [u8],
+    ) -> Self {
+        Self {
+            stream,
+            cipher,
+            prefix,
+            associated_data,
+            index: 0,
+            buffer: Vec::new(),
+            position: 0,
+            finished: false,
+            failed: false,
+        }
+    }
+
+    ///Read and open the next chunk, a chunk shorter than the others is the last one
+    fn open_chunk(&mut self) -> io::Result<()> {
+        let mut sealed = vec![0_u8; CHUNK_SIZE + TAG_SIZE];
+        let mut size = 0;
+        while size < sealed.len() {
+            match self.stream.read(&mut sealed[size..]) {
+                Ok(0) => break,
+                Ok(read) => size += read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        let last = size < sealed.len();
+        let nonce = chunk_nonce(&self.prefix, self.index, last);
+        let payload = Payload {
+            msg: &sealed[..size],
+            aad: self.associated_data,
+        };
+        self.buffer = self.cipher.decrypt(&nonce, payload).map_err(|_| {
+            self.failed = true;
+            io::Error::new(ErrorKind::InvalidData, "authentication failed")
+        })?;
+        self.position = 0;
+        self.finished = last;
+        self.index = self.index.wrapping_add(1);
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.buffer.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.open_chunk()?;
+        }
+        let size = buf.len().min(self.buffer.len() - self.position);
+        buf[..size].copy_from_slice(&self.buffer[self.position..self.position + size]);
+        self.position += size;
+        Ok(size)
+    }
+}
@@ -10,9 +10,69 @@ use crate::DataType;
 #[derive(Debug)]
 pub enum Error {
     CompressionError(yazi::Error),
-    DecodeError,
+    ///The data could not be decoded, `offset` is counted in bytes from the start of the decompressed records
+    DecodeError {
+        offset: u64,
+        record: usize,
+        key: Option<String>,
+        reason: DecodeReason,
+    },
     FileError(io::Error),
     ConversionError(DataType),
+    NotSmollFile,
+    UnsupportedVersion(u8),
+    UnsupportedFlags(u16),
+    ///The file is compressed with an unknown algorithm, or one whose cargo feature is disabled
+    UnsupportedCompression(u8),
+    ///The record at index `record`, starting `offset` bytes into the decompressed data, is corrupt
+    ChecksumMismatch {
+        record: usize,
+        offset: u64,
+    },
+    KeyTooLarge(usize),
+    ValueTooLarge(usize),
+    TooManyEntries(usize),
+    RecordTooLarge(u64),
+    ///The file is encrypted, it can only be loaded with `SmollDB::load_encrypted` from the `encryption` feature
+    Encrypted,
+    ///The key is wrong, or the encrypted file was altered, truncated or isn't encrypted at all
+    DecryptionFailed,
+    ///The file was not backed up with [`BackupOptions::indexed`](crate::BackupOptions::indexed)
+    NotIndexed,
+    ///The footer or the index of an indexed file is damaged
+    CorruptIndex,
+    ///The file is an incremental backup, it can only be loaded with
+    ///[`SmollDB::load_incremental`](crate::SmollDB::load_incremental)
+    Incremental,
+    ///A timestamp is too far from the Unix epoch to be stored
+    TimeOutOfRange,
+    ///Lists and maps are nested deeper than the 64 levels a file can hold
+    NestingTooDeep,
+    ///The base of an incremental backup isn't the last full backup of the database on file
+    BaseMismatch,
+    ///The incremental backup at this position in the chain doesn't follow the base and the previous ones
+    BrokenDeltaChain(usize),
+}
+
+///Why decoding failed, carried by [`Error::DecodeError`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DecodeReason {
+    ///The data ended in the middle of a record
+    UnexpectedEof,
+    ///A key or a string value is not valid UTF-8
+    InvalidUtf8,
+    ///The type tag of a value doesn't match any [`DataType`]
+    UnknownTypeTag(u8),
+    ///A char value is not a valid Unicode scalar value
+    InvalidChar(u32),
+    ///Lists and maps are nested deeper than the 64 levels that can be loaded
+    NestingTooDeep,
+    ///A timestamp or a duration is out of range
+    InvalidTime,
+    ///A length doesn't fit in a `usize` on this platform
+    LengthOverflow,
+    ///The length of a record doesn't match the size of its content
+    RecordSizeMismatch,
 }
 
 impl From<io::Error> for Error {
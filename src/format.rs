@@ -0,0 +1,225 @@
+use crate::{
+    compression::{Compression, COMPRESSION_FLAGS},
+    Error, Result,
+};
+use std::io::{ErrorKind, Read, Write};
+
+///Bytes every .smoll file starts with
+pub(crate) const MAGIC: [u8; 5] = *b"SMOLL";
+///Format version written by this version of the crate
+///
+///* version 0 files have no header, they are a bare Zlib stream with 8 byte big endian lengths
+///* version 1 stores key and value lengths as 8 byte big endian integers
+///* version 2 stores them as LEB128 varints, so files are portable across pointer widths
+///* version 3 prefixes every record with its length and follows it with its CRC32
+pub const VERSION: u8 = 3;
+///Version of the files written before the header existed
+pub(crate) const HEADERLESS_VERSION: u8 = 0;
+///Oldest format version that can still be loaded
+pub(crate) const LEGACY_VERSION: u8 = 1;
+///First format version with checksummed records
+pub(crate) const CHECKSUM_VERSION: u8 = 3;
+///Type tag of a record that removes its key instead of setting it
+pub(crate) const TOMBSTONE_ID: u8 = u8::MAX;
+///Flag of files whose payload is encrypted, followed by the salt and nonce prefix
+pub(crate) const ENCRYPTED_FLAG: u16 = 0x0010;
+///Flag of files with uncompressed records, followed by an index of their offsets and a [`Footer`]
+pub(crate) const INDEXED_FLAG: u16 = 0x0020;
+///Flag of incremental backups, holding only the changes since the previous backup.
+///The header is followed by a [`DeltaHeader`]
+pub(crate) const DELTA_FLAG: u16 = 0x0040;
+///Feature flags understood by this version of the crate
+///
+///* bits 0-3 hold the id of the [`Compression`] of the records
+///* bit 4 is [`ENCRYPTED_FLAG`]
+///* bit 5 is [`INDEXED_FLAG`]
+///* bit 6 is [`DELTA_FLAG`]
+pub(crate) const KNOWN_FLAGS: u16 = COMPRESSION_FLAGS | ENCRYPTED_FLAG | INDEXED_FLAG | DELTA_FLAG;
+
+///Uncompressed header placed in front of the encoded database
+///
+///Layout:
+///* 5 bytes of magic, always `SMOLL`
+///* 1 byte of format version
+///* 2 bytes of feature flags, big endian
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Header {
+    pub(crate) version: u8,
+    pub(crate) flags: u16,
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            flags: 0,
+        }
+    }
+}
+
+impl Header {
+    pub(crate) const SIZE: usize = MAGIC.len() + 3;
+
+    pub(crate) fn write(&self, stream: &mut impl Write) -> Result<()> {
+        let mut buffer = [0_u8; Self::SIZE];
+        buffer[..MAGIC.len()].copy_from_slice(&MAGIC);
+        buffer[MAGIC.len()] = self.version;
+        buffer[MAGIC.len() + 1..].copy_from_slice(&self.flags.to_be_bytes());
+        stream.write_all(&buffer)?;
+        Ok(())
+    }
+
+    ///Read and validate the header, leaving `stream` positioned at the start of the payload
+    pub(crate) fn read(stream: &mut impl Read) -> Result<Self> {
+        let mut buffer = [0_u8; Self::SIZE];
+        stream
+            .read_exact(&mut buffer)
+            .map_err(|err| match err.kind() {
+                ErrorKind::UnexpectedEof => Error::NotSmollFile,
+                _ => Error::FileError(err),
+            })?;
+        if buffer[..MAGIC.len()] != MAGIC {
+            return Err(Error::NotSmollFile);
+        }
+        let version = buffer[MAGIC.len()];
+        if !(LEGACY_VERSION..=VERSION).contains(&version) {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let flags = u16::from_be_bytes([buffer[MAGIC.len() + 1], buffer[MAGIC.len() + 2]]);
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::UnsupportedFlags(flags));
+        }
+        Compression::from_flags(flags)?;
+        Ok(Self { version, flags })
+    }
+
+    ///Read the header of a file of any known format version from the start of `encoded_data`,
+    ///returning it with the payload that follows.
+    ///Data starting like a Zlib stream instead of the magic is taken for a [`HEADERLESS_VERSION`] file
+    pub(crate) fn read_any(encoded_data: &[u8]) -> Result<(Self, &[u8])> {
+        if encoded_data.starts_with(&MAGIC) {
+            let mut payload = encoded_data;
+            let header = Self::read(&mut payload)?;
+            return Ok((header, payload));
+        }
+        if !is_zlib(encoded_data) {
+            return Err(Error::NotSmollFile);
+        }
+        let header = Self {
+            version: HEADERLESS_VERSION,
+            flags: 0,
+        };
+        Ok((header, encoded_data))
+    }
+
+    ///Read only the format version of the file starting with `encoded_data`,
+    ///versions and flags this crate doesn't understand are reported too
+    pub(crate) fn read_version(encoded_data: &[u8]) -> Result<u8> {
+        match encoded_data.strip_prefix(&MAGIC) {
+            Some([version, ..]) => Ok(*version),
+            None if is_zlib(encoded_data) => Ok(HEADERLESS_VERSION),
+            _ => Err(Error::NotSmollFile),
+        }
+    }
+
+    pub(crate) fn compression(&self) -> Result<Compression> {
+        Compression::from_flags(self.flags)
+    }
+
+    pub(crate) fn is_encrypted(&self) -> bool {
+        self.flags & ENCRYPTED_FLAG != 0
+    }
+
+    pub(crate) fn is_indexed(&self) -> bool {
+        self.flags & INDEXED_FLAG != 0
+    }
+
+    pub(crate) fn is_delta(&self) -> bool {
+        self.flags & DELTA_FLAG != 0
+    }
+}
+
+///Uncompressed header of incremental backups, placed after the [`Header`], links them to their base
+///
+///Layout:
+///* 4 bytes of CRC32 of the whole base file, big endian
+///* 8 bytes of position in the chain of incremental backups of the base, starting from 1, big endian
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct DeltaHeader {
+    pub(crate) base_checksum: u32,
+    pub(crate) sequence: u64,
+}
+
+impl DeltaHeader {
+    pub(crate) fn write(&self, stream: &mut impl Write) -> Result<()> {
+        stream.write_all(&self.base_checksum.to_be_bytes())?;
+        stream.write_all(&self.sequence.to_be_bytes())?;
+        Ok(())
+    }
+
+    pub(crate) fn read(stream: &mut impl Read) -> Result<Self> {
+        let mut base_checksum = [0_u8; 4];
+        let mut sequence = [0_u8; 8];
+        stream
+            .read_exact(&mut base_checksum)
+            .and_then(|_| stream.read_exact(&mut sequence))?;
+        Ok(Self {
+            base_checksum: u32::from_be_bytes(base_checksum),
+            sequence: u64::from_be_bytes(sequence),
+        })
+    }
+}
+
+///Trailer of indexed files, locates the index
+///
+///Layout:
+///* 8 bytes of index offset, counted from the end of the header, big endian
+///* 8 bytes of index size, big endian
+///* 4 bytes of CRC32 of the index, big endian
+///
+///The index is a varint count of entries, followed by every key and the offset of its record,
+///both prefixed by their varint length, sorted by key
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Footer {
+    pub(crate) index_offset: u64,
+    pub(crate) index_size: u64,
+    pub(crate) checksum: u32,
+}
+
+impl Footer {
+    pub(crate) const SIZE: usize = 20;
+
+    pub(crate) fn write(&self, stream: &mut impl Write) -> Result<()> {
+        let mut buffer = [0_u8; Self::SIZE];
+        buffer[..8].copy_from_slice(&self.index_offset.to_be_bytes());
+        buffer[8..16].copy_from_slice(&self.index_size.to_be_bytes());
+        buffer[16..].copy_from_slice(&self.checksum.to_be_bytes());
+        stream.write_all(&buffer)?;
+        Ok(())
+    }
+
+    pub(crate) fn read(stream: &mut impl Read) -> Result<Self> {
+        let mut index_offset = [0_u8; 8];
+        let mut index_size = [0_u8; 8];
+        let mut checksum = [0_u8; 4];
+        stream
+            .read_exact(&mut index_offset)
+            .and_then(|_| stream.read_exact(&mut index_size))
+            .and_then(|_| stream.read_exact(&mut checksum))
+            .map_err(|err| match err.kind() {
+                ErrorKind::UnexpectedEof => Error::CorruptIndex,
+                _ => Error::FileError(err),
+            })?;
+        Ok(Self {
+            index_offset: u64::from_be_bytes(index_offset),
+            index_size: u64::from_be_bytes(index_size),
+            checksum: u32::from_be_bytes(checksum),
+        })
+    }
+}
+
+///Check if `encoded_data` starts like a Zlib stream, as files without a header do
+fn is_zlib(encoded_data: &[u8]) -> bool {
+    matches!(encoded_data, [method, flags, ..]
+        if method & 0x0F == 8 && u16::from_be_bytes([*method, *flags]) % 31 == 0)
+}
@@ -0,0 +1,130 @@
+use crate::{
+    compression::Compression,
+    decoder::{is_valid_record, Decoder},
+    format::{Header, CHECKSUM_VERSION},
+    DataType, DecodeReason, Error, LoadOptions, Result, SmollDB,
+};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+///Append-only log of the mutations applied on top of a snapshot
+///
+///The log starts with the usual [`Header`] followed by uncompressed, checksummed records,
+///removed keys are stored as tombstones
+#[derive(Debug)]
+pub(crate) struct Journal {
+    snapshot: PathBuf,
+    file: File,
+    record: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl Journal {
+    ///Open the log next to `snapshot` and replay it on top of `inner`, creating it if missing
+    ///
+    ///A torn record at the end of the log, left by a crash during an append, is discarded.
+    ///Any other damage fails without touching the log, so later records aren't lost
+    pub(crate) fn open(snapshot: &Path, inner: &mut HashMap<String, DataType>) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(snapshot.with_extension("smoll.log"))?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        if data.len() < Header::SIZE {
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            let header = Header {
+                flags: Compression::None.id(),
+                ..Header::default()
+            };
+            header.write(&mut file)?;
+            file.sync_all()?;
+        } else {
+            let mut encoded_data = data.as_slice();
+            let header = Header::read(&mut encoded_data)?;
+            if header.version < CHECKSUM_VERSION {
+                return Err(Error::UnsupportedVersion(header.version));
+            }
+            let options = LoadOptions::default();
+            let mut decoder = Decoder::new(encoded_data.to_vec(), header, &options);
+            let mut replayed_size = 0;
+            while !decoder.is_empty() {
+                match decoder.decode_record() {
+                    Ok((key, Some(value))) => inner.insert(key, value),
+                    Ok((key, None)) => inner.remove(&key),
+                    Err(
+                        err @ Error::DecodeError {
+                            reason: DecodeReason::UnexpectedEof,
+                            ..
+                        },
+                    ) => {
+                        //A damaged length reads past the end too, but valid records follow it
+                        let tail = &encoded_data[replayed_size as usize..];
+                        if (1..tail.len()).any(|start| is_valid_record(&tail[start..])) {
+                            return Err(err);
+                        }
+                        break;
+                    }
+                    Err(err) => return Err(err),
+                };
+                replayed_size = decoder.offset();
+            }
+            if replayed_size < encoded_data.len() as u64 {
+                file.set_len(Header::SIZE as u64 + replayed_size)?;
+            }
+            file.seek(SeekFrom::End(0))?;
+        }
+        Ok(Self {
+            snapshot: snapshot.to_owned(),
+            file,
+            record: Vec::new(),
+            error: None,
+        })
+    }
+
+    ///Append a mutation, a missing `value` records the removal of `key`
+    ///
+    ///After a failed write, or a value that can't be encoded, the journal stops appending,
+    ///the error is reported by [`sync`](Self::sync) until the next [`compact`](Self::compact)
+    pub(crate) fn append(&mut self, key: &str, value: Option<&DataType>) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut encoded_data = Vec::new();
+        if let Err(err) = SmollDB::encode_framed(&mut encoded_data, &mut self.record, key, value) {
+            self.error = Some(io::Error::new(ErrorKind::InvalidInput, err.to_string()));
+            return;
+        }
+        if let Err(err) = self.file.write_all(&encoded_data) {
+            self.error = Some(err);
+        }
+    }
+
+    ///Flush the log to disk
+    pub(crate) fn sync(&self) -> Result<()> {
+        match &self.error {
+            Some(err) => Err(io::Error::new(err.kind(), err.to_string()).into()),
+            None => Ok(self.file.sync_all()?),
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> &Path {
+        &self.snapshot
+    }
+
+    ///Drop every record from the log, once they are part of a fresh snapshot
+    pub(crate) fn compact(&mut self) -> Result<()> {
+        self.file.set_len(Header::SIZE as u64)?;
+        self.file.seek(SeekFrom::End(0))?;
+        self.file.sync_all()?;
+        self.error = None;
+        Ok(())
+    }
+}
@@ -91,12 +91,33 @@
 //!
 //!assert_eq!(str2,&str1)
 //!```
+mod compression;
 mod datatype;
 mod db;
+mod decoder;
+#[cfg(feature = "encryption")]
+mod encryption;
 mod error;
+mod format;
+mod journal;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod options;
+mod reader;
+mod recovery;
 
 pub use crate::{
+    compression::Compression,
     datatype::DataType,
     db::SmollDB,
-    error::{Error, Result},
+    error::{DecodeReason, Error, Result},
+    format::VERSION as FORMAT_VERSION,
+    options::{BackupOptions, LoadOptions},
+    reader::SmollReader,
+    recovery::RecoveryReport,
 };
+
+#[cfg(feature = "encryption")]
+pub use crate::encryption::EncryptionKey;
+#[cfg(feature = "mmap")]
+pub use crate::mmap::{DataRef, SmollView};
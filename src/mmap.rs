@@ -0,0 +1,125 @@
+use crate::{decoder::read_varint, reader::Index, DataType, Result};
+use memmap2::Mmap;
+use std::{fmt::Display, fs::File, io::Cursor, path::Path};
+
+///Value borrowed from a [`SmollView`]
+///
+///Strings and bytes point straight into the mapped file, every other type is decoded into a [`DataType`]
+#[derive(PartialEq, Debug, Clone)]
+pub enum DataRef<'a> {
+    STRING(&'a str),
+    BYTES(&'a [u8]),
+    VALUE(DataType),
+}
+
+impl DataRef<'_> {
+    ///Copy the value out of the file
+    pub fn into_owned(self) -> DataType {
+        match self {
+            DataRef::STRING(value) => DataType::STRING(value.to_owned()),
+            DataRef::BYTES(value) => DataType::BYTES(value.to_vec()),
+            DataRef::VALUE(value) => value,
+        }
+    }
+}
+
+impl Display for DataRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataRef::STRING(value) => write!(f, "{}", value),
+            DataRef::BYTES(value) => write!(f, "{:?}", value),
+            DataRef::VALUE(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+///Read-only database mapped in memory, created by [`SmollDB::mmap`](crate::SmollDB::mmap)
+///
+///The file must be backed up with [`BackupOptions::indexed`](crate::BackupOptions::indexed).
+///Only the index is decoded when opening, values are read from the mapped file on every
+///[`get`](Self::get), so processes mapping the same file share its pages.
+///Replacing the file with a new backup is safe, but it must never be modified in place while mapped
+#[derive(Debug)]
+pub struct SmollView {
+    map: Mmap,
+    index: Index,
+}
+
+impl SmollView {
+    pub(crate) fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut filename = path.as_ref().to_owned();
+        filename.set_extension("smoll");
+        let file = File::open(filename)?;
+        //SAFETY: the file must not be modified while mapped, backups never write into an existing
+        //file but replace it with a rename, so the mapping keeps the previous content
+        let map = unsafe { Mmap::map(&file)? };
+        let index = Index::read(&mut Cursor::new(&map[..]))?;
+        Ok(Self { map, index })
+    }
+
+    ///Get the value of `key`, `None` if it isn't there
+    pub fn get(&self, key: &str) -> Result<Option<DataRef<'_>>> {
+        let record = match self.index.record(key)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let record = &self.map[record.start as usize..record.end as usize];
+        if let Some(value) = borrow_value(key, record) {
+            return Ok(Some(value));
+        }
+        let value = self.index.decode_record(key, record.to_vec())?;
+        Ok(value.map(DataRef::VALUE))
+    }
+
+    ///Like [`SmollReader::contains_key`](crate::SmollReader::contains_key)
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    ///Like [`SmollReader::keys`](crate::SmollReader::keys)
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.index.keys()
+    }
+
+    ///Like [`SmollReader::len`](crate::SmollReader::len)
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    ///Like [`SmollReader::is_empty`](crate::SmollReader::is_empty)
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+///Borrow the value of a valid string or bytes `record` of `key`.
+///Other types and damaged records return `None`, they are left to the [`Decoder`](crate::decoder::Decoder)
+fn borrow_value<'a>(key: &str, record: &'a [u8]) -> Option<DataRef<'a>> {
+    let (record_size, length_size) = read_varint(record)?;
+    let record = &record[length_size..];
+    let record_size = usize::try_from(record_size).ok()?;
+    if record.len() != record_size.checked_add(4)? {
+        return None;
+    }
+    let (body, checksum) = record.split_at(record_size);
+    if crc32fast::hash(body).to_be_bytes() != checksum {
+        return None;
+    }
+    let (key_size, length_size) = read_varint(body)?;
+    let body = &body[length_size..];
+    let key_size = usize::try_from(key_size).ok()?;
+    if body.get(..key_size)? != key.as_bytes() {
+        return None;
+    }
+    let (tag, body) = body[key_size..].split_first()?;
+    let (value_size, length_size) = read_varint(body)?;
+    let value = &body[length_size..];
+    if value.len() as u64 != value_size {
+        return None;
+    }
+    match tag {
+        7 => std::str::from_utf8(value).ok().map(DataRef::STRING),
+        8 => Some(DataRef::BYTES(value)),
+        _ => None,
+    }
+}
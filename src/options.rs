@@ -0,0 +1,145 @@
+use crate::{decoder::MAX_VARINT_SIZE, Compression, Error, Result};
+
+///Size of the largest value with a fixed size, a 128-bit integer
+const LARGEST_FIXED_VALUE: usize = 16;
+
+///Options to configure how the database is written by [`SmollDB::backup_with_options`](crate::SmollDB::backup_with_options)
+///
+///Works like [`std::fs::OpenOptions`], every setter returns the options so calls can be chained
+/// # Example
+/// ```no_run
+///# use smolldb::{BackupOptions, SmollDB};
+///let mut database = SmollDB::default();
+///database.set("example", String::from("data"));
+///database.backup_with_options("myfile", BackupOptions::new().keep_previous(true)).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BackupOptions {
+    pub(crate) keep_previous: bool,
+    pub(crate) compression: Compression,
+    pub(crate) level: Option<u8>,
+    pub(crate) indexed: bool,
+    pub(crate) incremental_base: bool,
+}
+
+impl BackupOptions {
+    ///Create the default options, same as the ones used by [`SmollDB::backup`](crate::SmollDB::backup)
+    pub fn new() -> Self {
+        Self::default()
+    }
+    ///Keep the file being replaced as `<path>.smoll.bak`
+    pub fn keep_previous(&mut self, keep_previous: bool) -> &mut Self {
+        self.keep_previous = keep_previous;
+        self
+    }
+    ///Compress the records with `compression`, [`Zlib`](crate::Compression::Zlib) by default
+    pub fn compression(&mut self, compression: Compression) -> &mut Self {
+        self.compression = compression;
+        self
+    }
+    ///Compression level, from 0 to 10 for zlib and from 1 to 22 for zstd.
+    ///Higher is smaller and slower, by default the fastest level is used
+    pub fn level(&mut self, level: u8) -> &mut Self {
+        self.level = Some(level);
+        self
+    }
+    ///Store the records uncompressed, followed by an index of their offsets,
+    ///so [`SmollReader`](crate::SmollReader) can fetch single keys without loading the file.
+    ///The compression options are ignored
+    pub fn indexed(&mut self, indexed: bool) -> &mut Self {
+        self.indexed = indexed;
+        self
+    }
+    ///Start a chain of incremental backups from this file, the keys set or removed from now on
+    ///are tracked for [`SmollDB::backup_incremental`](crate::SmollDB::backup_incremental)
+    pub fn incremental_base(&mut self, incremental_base: bool) -> &mut Self {
+        self.incremental_base = incremental_base;
+        self
+    }
+}
+
+///Limits enforced by [`SmollDB::load_with_options`](crate::SmollDB::load_with_options)
+///and [`SmollDB::load_from_stream_with_options`](crate::SmollDB::load_from_stream_with_options)
+///
+///By default nothing is limited, set them when loading files that can't be trusted
+/// # Example
+/// ```no_run
+///# use smolldb::{LoadOptions, SmollDB};
+///let database = SmollDB::load_with_options("myfile", LoadOptions::new().max_entries(100)).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    max_key_size: Option<usize>,
+    max_value_size: Option<usize>,
+    max_entries: Option<usize>,
+    max_record_size: Option<usize>,
+}
+
+impl LoadOptions {
+    ///Create the default options, same as the ones used by [`SmollDB::load`](crate::SmollDB::load)
+    pub fn new() -> Self {
+        Self::default()
+    }
+    ///Fail with [`KeyTooLarge`](crate::Error::KeyTooLarge) on keys longer than `max_key_size` bytes
+    pub fn max_key_size(&mut self, max_key_size: usize) -> &mut Self {
+        self.max_key_size = Some(max_key_size);
+        self
+    }
+    ///Fail with [`ValueTooLarge`](crate::Error::ValueTooLarge) on strings and bytes longer than `max_value_size` bytes,
+    ///and on lists and maps whose encoded elements, nested ones included, take more than `max_value_size` bytes.
+    ///
+    ///Together with [`max_key_size`](Self::max_key_size) it also bounds the size of whole records,
+    ///unless [`max_record_size`](Self::max_record_size) is set
+    pub fn max_value_size(&mut self, max_value_size: usize) -> &mut Self {
+        self.max_value_size = Some(max_value_size);
+        self
+    }
+    ///Fail with [`TooManyEntries`](crate::Error::TooManyEntries) on files with more than `max_entries` records
+    pub fn max_entries(&mut self, max_entries: usize) -> &mut Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    ///Fail with [`RecordTooLarge`](crate::Error::RecordTooLarge) on records longer than `max_record_size` bytes,
+    ///before they are buffered
+    pub fn max_record_size(&mut self, max_record_size: usize) -> &mut Self {
+        self.max_record_size = Some(max_record_size);
+        self
+    }
+
+    pub(crate) fn check_key_size(&self, size: usize) -> Result<()> {
+        match self.max_key_size {
+            Some(max_key_size) if size > max_key_size => Err(Error::KeyTooLarge(size)),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_value_size(&self, size: usize) -> Result<()> {
+        match self.max_value_size {
+            Some(max_value_size) if size > max_value_size => Err(Error::ValueTooLarge(size)),
+            _ => Ok(()),
+        }
+    }
+
+    ///Check the length a record declares, before reading it
+    pub(crate) fn check_record_size(&self, size: u64) -> Result<()> {
+        let max_record_size = match (self.max_record_size, self.max_key_size, self.max_value_size) {
+            (Some(max_record_size), _, _) => max_record_size,
+            (None, Some(max_key_size), Some(max_value_size)) => (2 * MAX_VARINT_SIZE + 1)
+                .saturating_add(max_key_size)
+                .saturating_add(max_value_size.max(LARGEST_FIXED_VALUE)),
+            _ => return Ok(()),
+        };
+        if size > max_record_size as u64 {
+            return Err(Error::RecordTooLarge(size));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_entries(&self, entries: usize) -> Result<()> {
+        match self.max_entries {
+            Some(max_entries) if entries > max_entries => Err(Error::TooManyEntries(entries)),
+            _ => Ok(()),
+        }
+    }
+}
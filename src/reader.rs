@@ -0,0 +1,179 @@
+use crate::{
+    decoder::Decoder,
+    format::{Footer, Header},
+    DataType, Error, LoadOptions, Result,
+};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::{Bound, Range},
+    path::Path,
+};
+
+///Read-only view of a .smoll file backed up with [`BackupOptions::indexed`](crate::BackupOptions::indexed)
+///
+///Only the index of the file is loaded, every [`get`](Self::get) seeks to the record and decodes
+///just that one, so large backups can be queried cheaply
+/// # Example
+/// ```no_run
+///# use smolldb::{BackupOptions, DataType, SmollDB, SmollReader};
+///let mut database = SmollDB::default();
+///database.set("example", String::from("data"));
+///database.backup_with_options("myfile", BackupOptions::new().indexed(true)).unwrap();
+///let mut reader = SmollReader::open("myfile").unwrap();
+///assert_eq!(reader.get("example").unwrap(), Some(DataType::STRING(String::from("data"))));
+/// ```
+#[derive(Debug)]
+pub struct SmollReader<R = File> {
+    stream: R,
+    index: Index,
+}
+
+impl SmollReader<File> {
+    ///Open an indexed database from file, path doesn't need the .smoll extention
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut filename = path.as_ref().to_owned();
+        filename.set_extension("smoll");
+        Self::from_stream(File::open(filename)?)
+    }
+}
+
+impl<R: Read + Seek> SmollReader<R> {
+    ///Open an indexed database from a generic seekable stream, which must start with the database
+    pub fn from_stream(mut stream: R) -> Result<Self> {
+        let index = Index::read(&mut stream)?;
+        Ok(Self { stream, index })
+    }
+
+    ///Fetch the value of `key` from the file, `None` if it isn't there
+    pub fn get(&mut self, key: &str) -> Result<Option<DataType>> {
+        let record = match self.index.record(key)? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        self.stream.seek(SeekFrom::Start(record.start))?;
+        let mut encoded_data = vec![0_u8; (record.end - record.start) as usize];
+        self.stream.read_exact(&mut encoded_data)?;
+        self.index.decode_record(key, encoded_data)
+    }
+
+    ///Whether `key` is stored in the file, found in the index without reading its record
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    ///Keys stored in the file, in sorted order
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.index.keys()
+    }
+
+    ///Number of keys stored in the file
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    ///Whether the file holds no keys
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+}
+
+///Index of an indexed file, locating the record of every key
+#[derive(Debug)]
+pub(crate) struct Index {
+    header: Header,
+    offsets: BTreeMap<String, u64>,
+    ///Offset of the empty record that ends the records
+    records_end: u64,
+}
+
+impl Index {
+    ///Read the header, the footer and the index of the file in `stream`
+    pub(crate) fn read(stream: &mut (impl Read + Seek)) -> Result<Self> {
+        stream.seek(SeekFrom::Start(0))?;
+        let header = Header::read(stream)?;
+        if header.is_encrypted() {
+            return Err(Error::Encrypted);
+        }
+        if !header.is_indexed() {
+            return Err(Error::NotIndexed);
+        }
+        let size = stream.seek(SeekFrom::End(0))?;
+        let records_size = size
+            .checked_sub((Header::SIZE + Footer::SIZE) as u64)
+            .ok_or(Error::CorruptIndex)?;
+        stream.seek(SeekFrom::End(-(Footer::SIZE as i64)))?;
+        let footer = Footer::read(stream)?;
+        if footer.index_offset.checked_add(footer.index_size) != Some(records_size)
+            || footer.index_offset == 0
+        {
+            return Err(Error::CorruptIndex);
+        }
+        stream.seek(SeekFrom::Start(Header::SIZE as u64 + footer.index_offset))?;
+        let mut index = vec![0_u8; footer.index_size as usize];
+        stream.read_exact(&mut index)?;
+        if crc32fast::hash(&index) != footer.checksum {
+            return Err(Error::CorruptIndex);
+        }
+        let header = Header { flags: 0, ..header };
+        let offsets = Decoder::new(index, header, &LoadOptions::default()).decode_index()?;
+        Ok(Self {
+            header,
+            offsets,
+            records_end: footer.index_offset - 1,
+        })
+    }
+
+    pub(crate) fn contains_key(&self, key: &str) -> bool {
+        self.offsets.contains_key(key)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &str> {
+        self.offsets.keys().map(String::as_str)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    ///Position in the file of the record of `key`, which ends where the record of the next key starts
+    pub(crate) fn record(&self, key: &str) -> Result<Option<Range<u64>>> {
+        let start = match self.offsets.get(key) {
+            Some(offset) => *offset,
+            None => return Ok(None),
+        };
+        let end = match self
+            .offsets
+            .range::<str, _>((Bound::Excluded(key), Bound::Unbounded))
+            .next()
+        {
+            Some((_, next_offset)) => *next_offset,
+            None => self.records_end,
+        };
+        if start > end || end > self.records_end {
+            return Err(Error::CorruptIndex);
+        }
+        let records_start = Header::SIZE as u64;
+        Ok(Some(records_start + start..records_start + end))
+    }
+
+    ///Decode the record of `key` read from the file
+    pub(crate) fn decode_record(
+        &self,
+        key: &str,
+        encoded_data: Vec<u8>,
+    ) -> Result<Option<DataType>> {
+        let options = LoadOptions::default();
+        let mut decoder = Decoder::new(encoded_data, self.header, &options);
+        let (record_key, value) = decoder.decode_record()?;
+        if record_key != key || !decoder.is_empty() {
+            return Err(Error::CorruptIndex);
+        }
+        Ok(value)
+    }
+}
@@ -0,0 +1,25 @@
+use crate::Error;
+
+///What [`SmollDB::load_lenient`](crate::SmollDB::load_lenient) had to give up on to load a damaged file
+///
+///Offsets are counted in bytes from the start of the decompressed records
+#[derive(Debug, Default)]
+pub struct RecoveryReport {
+    ///Number of records decoded cleanly and kept in the database
+    pub recovered_records: usize,
+    ///Number of corrupt records, each one may hide more records in its skipped bytes
+    pub skipped_records: usize,
+    ///Number of bytes dropped while looking for the next valid record
+    pub skipped_bytes: u64,
+    ///True if the compressed data was cut short or damaged, so the end of the file is lost
+    pub truncated: bool,
+    ///Why each skipped record could not be decoded
+    pub errors: Vec<Error>,
+}
+
+impl RecoveryReport {
+    ///True if the whole file was loaded
+    pub fn is_clean(&self) -> bool {
+        self.skipped_records == 0 && !self.truncated
+    }
+}
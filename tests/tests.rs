@@ -1,7 +1,26 @@
-use std::{fs::OpenOptions, io::Seek};
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::clone_on_copy,
+    clippy::needless_borrows_for_generic_args,
+    clippy::suspicious_open_options,
+    clippy::useless_vec,
+    noop_method_call
+)]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File, OpenOptions},
+    io::{Cursor, Seek, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use serial_test::serial;
-use smolldb::{DataType, SmollDB};
+use smolldb::{
+    BackupOptions, Compression, DataType, DecodeReason, Error, LoadOptions, SmollDB, SmollReader,
+    FORMAT_VERSION,
+};
+use yazi::{compress, CompressionLevel, Format};
 
 #[test]
 fn set_and_get() {
@@ -84,6 +103,76 @@ fn backup_and_load_int64() {
     assert_eq!(db, db_copy);
 }
 
+#[test]
+#[serial]
+fn backup_and_load_uint8() {
+    let mut db = SmollDB::default();
+    db.set("element", u8::MAX);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+#[serial]
+fn backup_and_load_uint16() {
+    let mut db = SmollDB::default();
+    db.set("element", u16::MAX);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+#[serial]
+fn backup_and_load_uint32() {
+    let mut db = SmollDB::default();
+    db.set("element", u32::MAX);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+#[serial]
+fn backup_and_load_uint64() {
+    let mut db = SmollDB::default();
+    db.set("element", u64::MAX);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+#[serial]
+fn backup_and_load_int128() {
+    let mut db = SmollDB::default();
+    db.set("element", i128::MIN);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+#[serial]
+fn backup_and_load_uint128() {
+    let mut db = SmollDB::default();
+    db.set("element", u128::MAX);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+#[serial]
+fn backup_and_load_char() {
+    let mut db = SmollDB::default();
+    db.set("element", '🦀');
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+}
+
 #[test]
 #[serial]
 fn backup_and_load_float32() {
@@ -286,6 +375,213 @@ fn test_db_extract(){
     assert_eq!(bytes_data, *bytes_data_extracted);
 }
 
+#[test]
+fn extract_unsigned() {
+    let mut database = SmollDB::default();
+    database.set("id", u64::MAX);
+    database.set("counter", 7_u32);
+
+    let id: u64 = database.extract(&"id").unwrap().unwrap();
+    assert_eq!(id, u64::MAX);
+    let counter: &u32 = database.extract(&"counter").unwrap().unwrap();
+    assert_eq!(*counter, 7);
+    let result: Result<i64, Error> = database.extract(&"id").unwrap();
+    assert!(matches!(
+        result,
+        Err(Error::ConversionError(DataType::UINT64(u64::MAX)))
+    ));
+}
+
+#[test]
+#[serial]
+fn backup_and_load_null() {
+    let mut db = SmollDB::default();
+    db.set("element", None::<i32>);
+    db.set("other", Some(3_i32));
+    assert_eq!(*db.get(&"element").unwrap(), DataType::NULL);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+
+    let element: Option<i32> = db_copy.extract(&"element").unwrap().unwrap();
+    assert_eq!(element, None);
+    let other: Option<&i32> = db_copy.extract(&"other").unwrap().unwrap();
+    assert_eq!(other, Some(&3));
+    let result: Result<i32, Error> = db_copy.extract(&"element").unwrap();
+    assert!(matches!(
+        result,
+        Err(Error::ConversionError(DataType::NULL))
+    ));
+}
+
+#[test]
+#[serial]
+fn backup_and_load_list() {
+    let mut db = SmollDB::default();
+    db.set("tags", vec![String::from("small"), String::from("fast")]);
+    db.set("scores", [3_u64, 1, 2].into_iter().collect::<DataType>());
+    db.set(
+        "mixed",
+        vec![
+            DataType::BOOL(true),
+            DataType::NULL,
+            DataType::LIST(vec![DataType::INT8(-1)]),
+            DataType::BYTES(vec![1, 2]),
+        ],
+    );
+    db.set("bytes", vec![1, 2, 3]);
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+
+    assert_eq!(
+        *db_copy.get(&"bytes").unwrap(),
+        DataType::BYTES(vec![1, 2, 3])
+    );
+    let scores: Vec<u64> = db_copy.extract(&"scores").unwrap().unwrap();
+    assert_eq!(scores, vec![3, 1, 2]);
+    let tags: Vec<String> = db_copy.extract(&"tags").unwrap().unwrap();
+    assert_eq!(tags, vec![String::from("small"), String::from("fast")]);
+    let mixed: &Vec<DataType> = db_copy.extract(&"mixed").unwrap().unwrap();
+    assert_eq!(mixed.len(), 4);
+    let result: Result<Vec<bool>, Error> = db_copy.extract(&"mixed").unwrap();
+    assert!(matches!(
+        result,
+        Err(Error::ConversionError(DataType::NULL))
+    ));
+    assert_eq!(
+        db_copy.get(&"mixed").unwrap().to_string(),
+        "[true, null, [-1], [1, 2]]"
+    );
+}
+
+#[test]
+#[serial]
+fn backup_and_load_map() {
+    let address = BTreeMap::from([
+        (String::from("city"), DataType::from(String::from("Rome"))),
+        (String::from("zip"), DataType::from(100_u32)),
+    ]);
+    let profile = BTreeMap::from([
+        (String::from("name"), DataType::from(String::from("Mario"))),
+        (String::from("address"), DataType::from(address)),
+        (
+            String::from("emails"),
+            DataType::from(vec![String::from("mario@example.com")]),
+        ),
+    ]);
+    let mut db = SmollDB::default();
+    db.set("user:1", profile);
+    db.set("scores", HashMap::from([(String::from("math"), 7_i8)]));
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+
+    assert_eq!(
+        *db_copy.get_path(&"user:1", &["address", "city"]).unwrap(),
+        DataType::STRING(String::from("Rome"))
+    );
+    assert_eq!(
+        *db_copy.get_path(&"user:1", &["emails", "0"]).unwrap(),
+        DataType::STRING(String::from("mario@example.com"))
+    );
+    assert!(db_copy
+        .get_path(&"user:1", &["address", "street"])
+        .is_none());
+    assert!(db_copy.get_path(&"user:1", &["name", "first"]).is_none());
+    assert!(db_copy.get_path(&"user:2", &["name"]).is_none());
+
+    let scores: BTreeMap<String, i8> = db_copy.extract(&"scores").unwrap().unwrap();
+    assert_eq!(scores, BTreeMap::from([(String::from("math"), 7)]));
+    assert_eq!(db_copy.get(&"scores").unwrap().to_string(), "{math: 7}");
+}
+
+#[test]
+#[serial]
+fn backup_and_load_time() {
+    let created = UNIX_EPOCH + Duration::new(1_700_000_000, 500_000_000);
+    let born = UNIX_EPOCH - Duration::from_millis(1250);
+    let mut db = SmollDB::default();
+    db.set("now", SystemTime::now());
+    db.set("created", created);
+    db.set("born", born);
+    db.set("timeout", Duration::new(90, 1_000_000));
+    db.backup("database").unwrap();
+    let db_copy = SmollDB::load("database").unwrap();
+    assert_eq!(db, db_copy);
+
+    let extracted: SystemTime = db_copy.extract(&"born").unwrap().unwrap();
+    assert_eq!(extracted, born);
+    let timeout: &Duration = db_copy.extract(&"timeout").unwrap().unwrap();
+    assert_eq!(*timeout, Duration::from_millis(90_001));
+    assert_eq!(
+        db_copy.get(&"created").unwrap().to_string(),
+        "2023-11-14T22:13:20.5Z"
+    );
+    assert_eq!(
+        db_copy.get(&"born").unwrap().to_string(),
+        "1969-12-31T23:59:58.75Z"
+    );
+    assert_eq!(db_copy.get(&"timeout").unwrap().to_string(), "PT90.001S");
+    assert_eq!(
+        DataType::from(UNIX_EPOCH).to_string(),
+        "1970-01-01T00:00:00Z"
+    );
+    let ancient = UNIX_EPOCH - Duration::from_secs(70_000_000_000);
+    assert_eq!(DataType::from(ancient).to_string(), "-0249-10-15T19:33:20Z");
+    let far = UNIX_EPOCH + Duration::from_secs(253_402_300_800);
+    assert_eq!(DataType::from(far).to_string(), "+10000-01-01T00:00:00Z");
+
+    let times = vec![born, created];
+    db.set("times", times.clone());
+    assert_eq!(
+        Vec::<SystemTime>::try_from(db.get(&"times").unwrap()).unwrap(),
+        times
+    );
+    let timeouts = vec![Duration::from_secs(1), Duration::from_millis(5)];
+    db.set("timeouts", timeouts.clone());
+    let extracted: Vec<Duration> = db.extract(&"timeouts").unwrap().unwrap();
+    assert_eq!(extracted, timeouts);
+}
+
+#[test]
+fn time_far_from_epoch_does_not_panic() {
+    let mut db = SmollDB::default();
+    if let Some(oldest) = UNIX_EPOCH.checked_sub(Duration::from_secs(1 << 63)) {
+        db.set("oldest", oldest);
+        assert_eq!(
+            db.get(&"oldest").unwrap().to_string(),
+            "-292277022657-01-27T08:29:52Z"
+        );
+        let mut data = Vec::new();
+        db.backup_to_stream(&mut data).unwrap();
+        let db_copy = SmollDB::load_from_stream(&mut data.as_slice()).unwrap();
+        assert_eq!(db, db_copy);
+    }
+    if let Some(too_old) = UNIX_EPOCH.checked_sub(Duration::new(1 << 63, 1)) {
+        db.set("too_old", too_old);
+        let result = db.backup_to_stream(&mut Vec::new());
+        assert!(matches!(result, Err(Error::TimeOutOfRange)));
+    }
+}
+
+#[test]
+fn backup_rejects_deeply_nested_lists() {
+    let mut value = DataType::NULL;
+    for _ in 0..64 {
+        value = DataType::LIST(vec![value]);
+    }
+    let mut db = SmollDB::default();
+    db.set("list", value.clone());
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let db_copy = SmollDB::load_from_stream(&mut data.as_slice()).unwrap();
+    assert_eq!(db, db_copy);
+    db.set("list", DataType::LIST(vec![value]));
+    let result = db.backup_to_stream(&mut Vec::new());
+    assert!(matches!(result, Err(Error::NestingTooDeep)));
+}
+
 #[test]
 fn test_db_extract_clone(){
     let mut database = SmollDB::default();
@@ -328,4 +624,892 @@ fn test_db_extract_clone(){
     assert_eq!(float64_data, float64_data_extracted);
     assert_eq!(string_data, string_data_extracted);
     assert_eq!(bytes_data, bytes_data_extracted);
-}
\ No newline at end of file
+}
+#[test]
+fn load_rejects_foreign_data() {
+    let garbage = compress(b"not a database", Format::Zlib, CompressionLevel::BestSpeed).unwrap();
+    let mut garbage = Cursor::new(garbage);
+    let result = SmollDB::load_from_stream(&mut garbage);
+    assert!(matches!(result, Err(Error::NotSmollFile)));
+    let mut empty = Cursor::new(Vec::new());
+    let result = SmollDB::load_from_stream(&mut empty);
+    assert!(matches!(result, Err(Error::NotSmollFile)));
+}
+
+#[test]
+fn load_rejects_unsupported_version() {
+    let mut data = Vec::new();
+    SmollDB::default().backup_to_stream(&mut data).unwrap();
+    data[5] = u8::MAX;
+    let result = SmollDB::load_from_stream(&mut data.as_slice());
+    assert!(matches!(result, Err(Error::UnsupportedVersion(u8::MAX))));
+}
+
+#[test]
+fn load_legacy_fixed_length_format() {
+    let mut records = Vec::new();
+    records.extend(3_u64.to_be_bytes());
+    records.extend(b"key");
+    records.push(7);
+    records.extend(5_u64.to_be_bytes());
+    records.extend(b"value");
+    let headerless = compress(&records, Format::Zlib, CompressionLevel::BestSpeed).unwrap();
+    let db = SmollDB::load_from_stream(&mut headerless.as_slice()).unwrap();
+    assert_eq!(
+        *db.get(&"key").unwrap(),
+        DataType::STRING(String::from("value"))
+    );
+    let mut data = b"SMOLL\x01\x00\x00".to_vec();
+    data.extend(headerless);
+    let db = SmollDB::load_from_stream(&mut data.as_slice()).unwrap();
+    assert_eq!(
+        *db.get(&"key").unwrap(),
+        DataType::STRING(String::from("value"))
+    );
+    records.extend(3_u64.to_be_bytes());
+    records.extend(b"ke");
+    let damaged = compress(&records, Format::Zlib, CompressionLevel::BestSpeed).unwrap();
+    let result = SmollDB::load_from_stream(&mut damaged.as_slice());
+    assert!(matches!(
+        result,
+        Err(Error::DecodeError {
+            record: 1,
+            reason: DecodeReason::UnexpectedEof,
+            ..
+        })
+    ));
+}
+
+#[test]
+#[serial]
+fn migrate_headerless_format() {
+    let mut records = Vec::new();
+    records.extend(3_u64.to_be_bytes());
+    records.extend(b"key");
+    records.push(7);
+    records.extend(5_u64.to_be_bytes());
+    records.extend(b"value");
+    let data = compress(&records, Format::Zlib, CompressionLevel::BestSpeed).unwrap();
+    fs::write("headerless.smoll", data).unwrap();
+    assert_eq!(SmollDB::format_version("headerless").unwrap(), 0);
+    let db = SmollDB::load("headerless").unwrap();
+    assert_eq!(
+        *db.get(&"key").unwrap(),
+        DataType::STRING(String::from("value"))
+    );
+    let (db_copy, report) = SmollDB::load_lenient("headerless").unwrap();
+    assert!(report.is_clean());
+    assert_eq!(db, db_copy);
+
+    SmollDB::migrate("headerless", "migrated").unwrap();
+    assert_eq!(SmollDB::format_version("migrated").unwrap(), FORMAT_VERSION);
+    let db = SmollDB::load("migrated").unwrap();
+    assert_eq!(
+        *db.get(&"key").unwrap(),
+        DataType::STRING(String::from("value"))
+    );
+}
+
+#[test]
+#[serial]
+fn migrate_keeps_backup_options() {
+    let mut db = SmollDB::default();
+    db.set("key", 1);
+    db.backup_with_options(
+        "migrated",
+        BackupOptions::new()
+            .compression(Compression::None)
+            .indexed(true),
+    )
+    .unwrap();
+    SmollDB::migrate("migrated", "migrated").unwrap();
+    let mut reader = SmollReader::open("migrated").unwrap();
+    assert_eq!(reader.get("key").unwrap(), Some(DataType::INT32(1)));
+
+    let garbage = b"not a database".to_vec();
+    let result = SmollDB::format_version_from_stream(&mut garbage.as_slice());
+    assert!(matches!(result, Err(Error::NotSmollFile)));
+    let mut newer = Vec::new();
+    SmollDB::default().backup_to_stream(&mut newer).unwrap();
+    newer[5] = 9;
+    newer[7] = 0xFF;
+    let version = SmollDB::format_version_from_stream(&mut newer.as_slice()).unwrap();
+    assert_eq!(version, 9);
+}
+
+#[test]
+fn backup_and_load_long_values() {
+    let mut db = SmollDB::default();
+    db.set("k".repeat(300), "v".repeat(70_000));
+    db.set("bytes", vec![7_u8; 128]);
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let db_copy = SmollDB::load_from_stream(&mut data.as_slice()).unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+fn load_detects_corrupt_record() {
+    let mut records = Vec::new();
+    for (key, value) in [(b"first", 1_u8), (b"other", 2_u8)] {
+        let record = [&[5], &key[..], &[1, value]].concat();
+        records.push(record.len() as u8);
+        records.extend(&record);
+        records.extend(crc32fast::hash(&record).to_be_bytes());
+    }
+    let last = records.len() - 5;
+    records[last] ^= 0x10;
+    let mut data = b"SMOLL\x03\x00\x00".to_vec();
+    data.extend(compress(&records, Format::Zlib, CompressionLevel::BestSpeed).unwrap());
+    let result = SmollDB::load_from_stream(&mut data.as_slice());
+    assert!(matches!(
+        result,
+        Err(Error::ChecksumMismatch {
+            record: 1,
+            offset: 13
+        })
+    ));
+}
+
+#[test]
+#[serial]
+fn backup_keeps_previous_file() {
+    let mut db = SmollDB::default();
+    db.set("version", 1_i8);
+    db.backup("atomic").unwrap();
+    let first = SmollDB::load("atomic").unwrap();
+    db.set("version", 2_i8);
+    db.backup_with_options("atomic", BackupOptions::new().keep_previous(true))
+        .unwrap();
+    assert!(!Path::new("atomic.smoll.tmp").exists());
+    assert_eq!(SmollDB::load("atomic").unwrap(), db);
+    let mut previous = File::open("atomic.smoll.bak").unwrap();
+    assert_eq!(SmollDB::load_from_stream(&mut previous).unwrap(), first);
+}
+
+#[test]
+#[serial]
+fn journal_replays_mutations() {
+    let _ = fs::remove_file("journal.smoll");
+    let _ = fs::remove_file("journal.smoll.log");
+    let mut db = SmollDB::open("journal").unwrap();
+    db.set("kept", 1_i32);
+    db.set("removed", 2_i32);
+    db.set("kept", 3_i32);
+    db.remove(&"removed");
+    db.sync().unwrap();
+    drop(db);
+    let log = SmollDB::load_from_stream(&mut File::open("journal.smoll.log").unwrap()).unwrap();
+    assert_eq!(*log.get(&"kept").unwrap(), DataType::INT32(3));
+    let db = SmollDB::open("journal").unwrap();
+    assert_eq!(*db.get(&"kept").unwrap(), DataType::INT32(3));
+    assert!(!db.contains_key(&"removed"));
+}
+
+#[test]
+#[serial]
+fn journal_discards_torn_record() {
+    let _ = fs::remove_file("journal.smoll");
+    let _ = fs::remove_file("journal.smoll.log");
+    let mut db = SmollDB::open("journal").unwrap();
+    db.set("first", true);
+    db.set("second", false);
+    drop(db);
+    let log = OpenOptions::new()
+        .write(true)
+        .open("journal.smoll.log")
+        .unwrap();
+    let size = log.metadata().unwrap().len();
+    log.set_len(size - 2).unwrap();
+    let mut db = SmollDB::open("journal").unwrap();
+    assert!(db.contains_key(&"first"));
+    assert!(!db.contains_key(&"second"));
+    db.set("third", true);
+    drop(db);
+    let db = SmollDB::open("journal").unwrap();
+    assert!(db.contains_key(&"first"));
+    assert!(db.contains_key(&"third"));
+}
+
+#[test]
+#[serial]
+fn journal_keeps_records_after_corrupt_one() {
+    let _ = fs::remove_file("journal.smoll");
+    let _ = fs::remove_file("journal.smoll.log");
+    let mut db = SmollDB::open("journal").unwrap();
+    db.set("first", true);
+    db.set("second", false);
+    db.set("third", true);
+    drop(db);
+    let mut log = fs::read("journal.smoll.log").unwrap();
+    let second = log
+        .windows(6)
+        .position(|window| window == b"second")
+        .unwrap();
+    log[second] ^= 0x01;
+    fs::write("journal.smoll.log", &log).unwrap();
+    let result = SmollDB::open("journal");
+    assert!(matches!(
+        result,
+        Err(Error::ChecksumMismatch { record: 1, .. })
+    ));
+    assert_eq!(fs::read("journal.smoll.log").unwrap(), log);
+}
+
+#[test]
+#[serial]
+fn journal_keeps_records_after_corrupt_length() {
+    let _ = fs::remove_file("journal.smoll");
+    let _ = fs::remove_file("journal.smoll.log");
+    let mut db = SmollDB::open("journal").unwrap();
+    db.set("first", true);
+    db.set("second", false);
+    db.set("third", true);
+    drop(db);
+    let mut log = fs::read("journal.smoll.log").unwrap();
+    log[8] = 0x7F;
+    fs::write("journal.smoll.log", &log).unwrap();
+    let result = SmollDB::open("journal");
+    assert!(matches!(
+        result,
+        Err(Error::DecodeError {
+            record: 0,
+            reason: DecodeReason::UnexpectedEof,
+            ..
+        })
+    ));
+    assert_eq!(fs::read("journal.smoll.log").unwrap(), log);
+}
+
+#[test]
+#[serial]
+fn journal_compact_writes_snapshot() {
+    let _ = fs::remove_file("journal.smoll");
+    let _ = fs::remove_file("journal.smoll.log");
+    let mut db = SmollDB::open("journal").unwrap();
+    db.set("key", String::from("value"));
+    db.compact().unwrap();
+    db.set("other", 1_i8);
+    drop(db);
+    let snapshot = SmollDB::load("journal").unwrap();
+    assert!(snapshot.contains_key(&"key"));
+    assert!(!snapshot.contains_key(&"other"));
+    let db = SmollDB::open("journal").unwrap();
+    assert!(db.contains_key(&"key"));
+    assert!(db.contains_key(&"other"));
+}
+
+#[test]
+fn load_never_panics_on_damaged_data() {
+    let mut db = SmollDB::default();
+    db.set("string", String::from("S.M.O.L.L."));
+    db.set("bytes", vec![1_u8, 2, 3]);
+    db.set("int64", 64_i64);
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    for size in 0..data.len() {
+        let _ = SmollDB::load_from_stream(&mut &data[..size]);
+    }
+    for position in 0..data.len() {
+        let mut damaged = data.clone();
+        damaged[position] ^= 0xFF;
+        let _ = SmollDB::load_from_stream(&mut damaged.as_slice());
+    }
+    let mut records = vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x7F];
+    records.extend(b"tail");
+    let mut hostile = b"SMOLL\x03\x00\x00".to_vec();
+    hostile.extend(compress(&records, Format::Zlib, CompressionLevel::BestSpeed).unwrap());
+    assert!(SmollDB::load_from_stream(&mut hostile.as_slice()).is_err());
+}
+
+#[test]
+fn load_enforces_limits() {
+    let mut db = SmollDB::default();
+    db.set("key", String::from("value"));
+    db.set("other", vec![0_u8; 64]);
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let result = SmollDB::load_from_stream_with_options(
+        &mut data.as_slice(),
+        LoadOptions::new().max_key_size(4),
+    );
+    assert!(matches!(result, Err(Error::KeyTooLarge(5))));
+    let result = SmollDB::load_from_stream_with_options(
+        &mut data.as_slice(),
+        LoadOptions::new().max_value_size(32),
+    );
+    assert!(matches!(result, Err(Error::ValueTooLarge(64))));
+    let result = SmollDB::load_from_stream_with_options(
+        &mut data.as_slice(),
+        LoadOptions::new().max_entries(1),
+    );
+    assert!(matches!(result, Err(Error::TooManyEntries(2))));
+    let mut options = LoadOptions::new();
+    options.max_key_size(5).max_value_size(64).max_entries(2);
+    let db_copy = SmollDB::load_from_stream_with_options(&mut data.as_slice(), &options).unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+fn load_limits_lists_and_maps() {
+    let mut options = LoadOptions::new();
+    options.max_value_size(16).max_entries(1);
+    let load = |value: DataType| {
+        let mut db = SmollDB::default();
+        db.set("key", value);
+        let mut data = Vec::new();
+        db.backup_to_stream(&mut data).unwrap();
+        SmollDB::load_from_stream_with_options(&mut data.as_slice(), &options)
+    };
+    let result = load(DataType::LIST(vec![DataType::NULL; 100_000]));
+    assert!(matches!(result, Err(Error::ValueTooLarge(100_000))));
+    let map: BTreeMap<_, _> = (0..20).map(|i| (i.to_string(), DataType::NULL)).collect();
+    let result = load(DataType::MAP(map));
+    assert!(matches!(result, Err(Error::ValueTooLarge(20))));
+    let nested = DataType::LIST(vec![DataType::NULL; 4]);
+    let result = load(DataType::LIST(vec![nested.clone(); 4]));
+    assert!(matches!(result, Err(Error::ValueTooLarge(20))));
+    let value = DataType::LIST(vec![nested; 2]);
+    let db = load(value.clone()).unwrap();
+    assert_eq!(*db.get(&"key").unwrap(), value);
+}
+
+#[test]
+fn load_rejects_huge_records_before_buffering() {
+    let mut records = vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+    records.extend(vec![0_u8; 64 * 1024]);
+    let data = smoll_file(&records);
+    let mut options = LoadOptions::new();
+    options.max_key_size(64).max_value_size(1024);
+    let result = SmollDB::load_from_stream_with_options(&mut data.as_slice(), &options);
+    assert!(matches!(result, Err(Error::RecordTooLarge(size)) if size == 1 << 35));
+
+    let mut db = SmollDB::default();
+    db.set("key", String::from("value"));
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let result = SmollDB::load_from_stream_with_options(
+        &mut data.as_slice(),
+        LoadOptions::new().max_record_size(8),
+    );
+    assert!(matches!(result, Err(Error::RecordTooLarge(11))));
+}
+
+fn frame_records(records: &[&[u8]]) -> Vec<u8> {
+    let mut encoded_data = Vec::new();
+    for record in records {
+        encoded_data.push(record.len() as u8);
+        encoded_data.extend(*record);
+        encoded_data.extend(crc32fast::hash(record).to_be_bytes());
+    }
+    encoded_data
+}
+
+fn smoll_file(encoded_data: &[u8]) -> Vec<u8> {
+    let mut data = b"SMOLL\x03\x00\x00".to_vec();
+    data.extend(compress(encoded_data, Format::Zlib, CompressionLevel::BestSpeed).unwrap());
+    data
+}
+
+fn framed_records(records: &[&[u8]]) -> Vec<u8> {
+    smoll_file(&frame_records(records))
+}
+
+#[test]
+fn load_reports_decode_error_details() {
+    let data = framed_records(&[b"\x01a\x01\x05", b"\x03key\x2A\x00"]);
+    match SmollDB::load_from_stream(&mut data.as_slice()) {
+        Err(Error::DecodeError {
+            offset,
+            record,
+            key,
+            reason,
+        }) => {
+            assert_eq!(offset, 14);
+            assert_eq!(record, 1);
+            assert_eq!(key.as_deref(), Some("key"));
+            assert_eq!(reason, DecodeReason::UnknownTypeTag(0x2A));
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    let data = framed_records(&[b"\x02\xFF\xFE\x00\x01"]);
+    let result = SmollDB::load_from_stream(&mut data.as_slice());
+    assert!(matches!(
+        result,
+        Err(Error::DecodeError {
+            offset: 2,
+            record: 0,
+            key: None,
+            reason: DecodeReason::InvalidUtf8
+        })
+    ));
+    let data = framed_records(&[b"\x01a\x07\x09text"]);
+    let result = SmollDB::load_from_stream(&mut data.as_slice());
+    assert!(matches!(
+        result,
+        Err(Error::DecodeError {
+            reason: DecodeReason::UnexpectedEof,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn load_lenient_skips_corrupt_records() {
+    let mut encoded_data = frame_records(&[b"\x01a\x01\x01", b"\x01b\x01\x02", b"\x01c\x01\x03"]);
+    encoded_data[13] ^= 0xFF;
+    let data = smoll_file(&encoded_data);
+    assert!(SmollDB::load_from_stream(&mut data.as_slice()).is_err());
+    let (db, report) = SmollDB::load_from_stream_lenient(&mut data.as_slice()).unwrap();
+    assert!(!report.is_clean());
+    assert_eq!(report.recovered_records, 2);
+    assert_eq!(report.skipped_records, 1);
+    assert_eq!(report.skipped_bytes, 9);
+    assert!(!report.truncated);
+    assert!(matches!(
+        report.errors[..],
+        [Error::ChecksumMismatch {
+            record: 1,
+            offset: 9
+        }]
+    ));
+    assert_eq!(*db.get(&"a").unwrap(), DataType::INT8(1));
+    assert!(!db.contains_key(&"b"));
+    assert_eq!(*db.get(&"c").unwrap(), DataType::INT8(3));
+}
+
+#[test]
+fn load_lenient_keeps_records_before_truncation() {
+    let mut db = SmollDB::default();
+    for i in 0..1000 {
+        db.set(format!("key{}", i), format!("value{}", i * 7919 % 1000));
+    }
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let (db_copy, report) = SmollDB::load_from_stream_lenient(&mut data.as_slice()).unwrap();
+    assert!(report.is_clean());
+    assert_eq!(db, db_copy);
+    data.truncate(data.len() / 2);
+    let (db_copy, report) = SmollDB::load_from_stream_lenient(&mut data.as_slice()).unwrap();
+    assert!(report.truncated);
+    assert!(report.recovered_records > 0);
+    for (key, value) in db_copy {
+        assert_eq!(*db.get(&key).unwrap(), value);
+    }
+}
+
+#[test]
+fn backup_to_stream_writes_incrementally() {
+    struct ChunkWriter {
+        data: Vec<u8>,
+        largest_write: usize,
+    }
+    impl Write for ChunkWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest_write = self.largest_write.max(buf.len());
+            self.data.extend(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut db = SmollDB::default();
+    let mut seed = 12345_u32;
+    for i in 0..16_000 {
+        let value: Vec<u8> = (0..64)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 24) as u8
+            })
+            .collect();
+        db.set(format!("key{}", i), value);
+    }
+    let mut writer = ChunkWriter {
+        data: Vec::new(),
+        largest_write: 0,
+    };
+    db.backup_to_stream(&mut writer).unwrap();
+    assert!(writer.largest_write < writer.data.len() / 8);
+    let db_copy = SmollDB::load_from_stream(&mut writer.data.as_slice()).unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[test]
+fn load_from_stream_reads_incrementally() {
+    struct ChunkReader<'a> {
+        data: &'a [u8],
+    }
+    impl std::io::Read for ChunkReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = buf.len().min(self.data.len()).min(7);
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.data = &self.data[size..];
+            Ok(size)
+        }
+    }
+    let mut db = SmollDB::default();
+    for i in 0..2_000 {
+        db.set(format!("key{}", i), format!("value{}", i));
+    }
+    db.set("bytes", vec![7_u8; 100_000]);
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let db_copy = SmollDB::load_from_stream(&mut ChunkReader { data: &data }).unwrap();
+    assert_eq!(db, db_copy);
+
+    let truncated = &data[..data.len() / 2];
+    assert!(SmollDB::load_from_stream(&mut ChunkReader { data: truncated }).is_err());
+}
+
+fn compression_roundtrip(options: &BackupOptions) -> Vec<u8> {
+    let mut db = SmollDB::default();
+    for i in 0..500 {
+        db.set(format!("key{}", i), format!("value{}", i));
+    }
+    let mut data = Vec::new();
+    db.backup_to_stream_with_options(&mut data, options)
+        .unwrap();
+    let db_copy = SmollDB::load_from_stream(&mut data.as_slice()).unwrap();
+    assert_eq!(db, db_copy);
+    data
+}
+
+#[test]
+fn backup_with_compression_options() {
+    let fast = compression_roundtrip(&BackupOptions::new());
+    let small = compression_roundtrip(BackupOptions::new().level(10));
+    assert!(small.len() <= fast.len());
+
+    let raw = compression_roundtrip(BackupOptions::new().compression(Compression::None));
+    assert!(raw.len() > fast.len());
+    assert!(raw.windows(8).any(|window| window == b"value499"));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn backup_with_zstd() {
+    compression_roundtrip(BackupOptions::new().compression(Compression::Zstd));
+    compression_roundtrip(
+        BackupOptions::new()
+            .compression(Compression::Zstd)
+            .level(19),
+    );
+}
+
+#[cfg(feature = "lz4")]
+#[test]
+fn backup_with_lz4() {
+    compression_roundtrip(BackupOptions::new().compression(Compression::Lz4));
+}
+
+#[test]
+fn load_rejects_unknown_compression() {
+    let data = b"SMOLL\x03\x00\x0F".to_vec();
+    let result = SmollDB::load_from_stream(&mut data.as_slice());
+    assert!(matches!(result, Err(Error::UnsupportedCompression(15))));
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn backup_and_load_encrypted() {
+    use smolldb::EncryptionKey;
+
+    let mut db = SmollDB::default();
+    db.set("token", String::from("api-token-1234"));
+    db.set("blob", vec![42_u8; 200_000]);
+    let key = EncryptionKey::from_bytes([7; 32]);
+    let mut data = Vec::new();
+    db.backup_to_stream_encrypted(&mut data, &key).unwrap();
+    assert!(!data.windows(14).any(|window| window == b"api-token-1234"));
+    let db_copy = SmollDB::load_from_stream_encrypted(&mut data.as_slice(), &key).unwrap();
+    assert_eq!(db, db_copy);
+
+    let wrong_key = EncryptionKey::from_bytes([8; 32]);
+    let result = SmollDB::load_from_stream_encrypted(&mut data.as_slice(), &wrong_key);
+    assert!(matches!(result, Err(Error::DecryptionFailed)));
+
+    let mut tampered = data.clone();
+    let last = tampered.len() - 20;
+    tampered[last] ^= 1;
+    let result = SmollDB::load_from_stream_encrypted(&mut tampered.as_slice(), &key);
+    assert!(matches!(result, Err(Error::DecryptionFailed)));
+
+    let truncated = &data[..data.len() - 1];
+    let result = SmollDB::load_from_stream_encrypted(&mut &truncated[..], &key);
+    assert!(matches!(result, Err(Error::DecryptionFailed)));
+
+    let result = SmollDB::load_from_stream(&mut data.as_slice());
+    assert!(matches!(result, Err(Error::Encrypted)));
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn backup_and_load_encrypted_full_chunk() {
+    use smolldb::EncryptionKey;
+
+    let mut state = 0x2545_F491_u32;
+    let noise: Vec<u8> = (0..65_600)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let db = (65_400..65_600)
+        .map(|size| {
+            let mut db = SmollDB::default();
+            db.set("bytes", noise[..size].to_vec());
+            db
+        })
+        .find(|db| {
+            let mut data = Vec::new();
+            db.backup_to_stream(&mut data).unwrap();
+            data.len() - 8 == 64 * 1024
+        })
+        .unwrap();
+
+    let key = EncryptionKey::from_bytes([3; 32]);
+    let mut data = Vec::new();
+    db.backup_to_stream_encrypted(&mut data, &key).unwrap();
+    let db_copy = SmollDB::load_from_stream_encrypted(&mut data.as_slice(), &key).unwrap();
+    assert_eq!(db, db_copy);
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+#[serial]
+fn backup_and_load_encrypted_with_passphrase() {
+    use smolldb::EncryptionKey;
+
+    let mut db = SmollDB::default();
+    db.set("token", String::from("api-token-1234"));
+    let key = EncryptionKey::from_passphrase("correct horse battery staple");
+    db.backup_encrypted("encrypted", &key).unwrap();
+    let db_copy = SmollDB::load_encrypted("encrypted", &key).unwrap();
+    assert_eq!(db, db_copy);
+
+    let wrong_key = EncryptionKey::from_passphrase("incorrect horse battery staple");
+    let result = SmollDB::load_encrypted("encrypted", &wrong_key);
+    assert!(matches!(result, Err(Error::DecryptionFailed)));
+}
+
+#[test]
+fn backup_is_deterministic() {
+    let mut db = SmollDB::default();
+    let mut db_reversed = SmollDB::default();
+    for i in 0..1000 {
+        db.set(format!("key{}", i), i);
+        db_reversed.set(format!("key{}", 999 - i), 999 - i);
+    }
+    let mut data = Vec::new();
+    let mut data_reversed = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    db_reversed.backup_to_stream(&mut data_reversed).unwrap();
+    assert_eq!(data, data_reversed);
+}
+
+#[test]
+#[serial]
+fn indexed_backup_reads_single_keys() {
+    let mut db = SmollDB::default();
+    for i in 0..1000 {
+        db.set(format!("key{}", i), format!("value{}", i));
+    }
+    db.set("bytes", vec![0_u8; 300]);
+    db.set("int", 42_i64);
+    db.backup_with_options("indexed", BackupOptions::new().indexed(true))
+        .unwrap();
+
+    let mut reader = SmollReader::open("indexed").unwrap();
+    assert_eq!(reader.len(), 1002);
+    assert!(reader.contains_key("key500"));
+    assert_eq!(
+        reader.get("key500").unwrap(),
+        Some(DataType::from("value500".to_string()))
+    );
+    assert_eq!(reader.get("int").unwrap(), Some(DataType::INT64(42)));
+    assert_eq!(
+        reader.get("bytes").unwrap(),
+        Some(DataType::BYTES(vec![0; 300]))
+    );
+    assert_eq!(reader.get("missing").unwrap(), None);
+    assert_eq!(reader.keys().next(), Some("bytes"));
+
+    let db_copy = SmollDB::load("indexed").unwrap();
+    assert_eq!(db, db_copy);
+    let (db_copy, report) = SmollDB::load_lenient("indexed").unwrap();
+    assert_eq!(db, db_copy);
+    assert!(report.is_clean());
+}
+
+#[test]
+fn indexed_reader_rejects_damaged_index() {
+    let mut db = SmollDB::default();
+    db.set("key", String::from("value"));
+    let mut data = Vec::new();
+    db.backup_to_stream(&mut data).unwrap();
+    let result = SmollReader::from_stream(Cursor::new(&data));
+    assert!(matches!(result, Err(Error::NotIndexed)));
+
+    let mut data = Vec::new();
+    db.backup_to_stream_with_options(&mut data, BackupOptions::new().indexed(true))
+        .unwrap();
+    let index = data.len() - 22;
+    data[index] ^= 1;
+    let result = SmollReader::from_stream(Cursor::new(&data));
+    assert!(matches!(result, Err(Error::CorruptIndex)));
+
+    let result = SmollReader::from_stream(Cursor::new(&data[..data.len() - 1]));
+    assert!(matches!(result, Err(Error::CorruptIndex)));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+#[serial]
+fn mmap_borrows_values_from_file() {
+    use smolldb::DataRef;
+
+    let mut db = SmollDB::default();
+    db.set("string", String::from("mapped"));
+    db.set("bytes", vec![1_u8, 2, 3]);
+    db.set("float", 1.5_f64);
+    db.backup_with_options("mapped", BackupOptions::new().indexed(true))
+        .unwrap();
+
+    let view = SmollDB::mmap("mapped").unwrap();
+    assert_eq!(view.len(), 3);
+    assert_eq!(view.get("string").unwrap(), Some(DataRef::STRING("mapped")));
+    assert_eq!(view.get("bytes").unwrap(), Some(DataRef::BYTES(&[1, 2, 3])));
+    assert_eq!(
+        view.get("float").unwrap(),
+        Some(DataRef::VALUE(DataType::FLOAT64(1.5)))
+    );
+    assert_eq!(view.get("missing").unwrap(), None);
+
+    db.backup("mapped").unwrap();
+    assert!(matches!(SmollDB::mmap("mapped"), Err(Error::NotIndexed)));
+}
+
+#[test]
+#[serial]
+fn load_only_matching_keys() {
+    let mut db = SmollDB::default();
+    for i in 0..100 {
+        db.set(format!("users:{}", i), format!("user{}", i));
+        db.set(format!("groups:{}", i), i);
+    }
+    db.backup("filtered").unwrap();
+
+    let users = SmollDB::load_prefix("filtered", "users:").unwrap();
+    assert_eq!(users.into_iter().count(), 100);
+    let users = SmollDB::load_prefix("filtered", "users:").unwrap();
+    assert!(users.into_iter().all(|(key, _)| key.starts_with("users:")));
+
+    let db_copy = SmollDB::load_filtered("filtered", |key| key == "groups:7").unwrap();
+    assert_eq!(*db_copy.get(&"groups:7").unwrap(), DataType::INT32(7));
+    assert_eq!(db_copy.into_iter().count(), 1);
+}
+
+#[test]
+fn load_rejects_invalid_char() {
+    let data = framed_records(&[b"\x01c\x0F\x00\x00\xD8\x00"]);
+    match SmollDB::load_from_stream(&mut data.as_slice()) {
+        Err(Error::DecodeError { reason, .. }) => {
+            assert_eq!(reason, DecodeReason::InvalidChar(0xD800))
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn load_filtered_skips_rejected_values() {
+    let data = smoll_file(&frame_records(&[
+        b"\x03bad\xC8garbage",
+        b"\x04good\x03\x00\x00\x00\x01",
+    ]));
+    let db = SmollDB::load_from_stream_filtered(&mut data.as_slice(), |key| key != "bad").unwrap();
+    assert_eq!(*db.get(&"good").unwrap(), DataType::INT32(1));
+    assert!(SmollDB::load_from_stream(&mut data.as_slice()).is_err());
+}
+
+#[test]
+#[serial]
+fn incremental_backups_rebuild_the_database() {
+    let mut db = SmollDB::default();
+    db.set("kept", String::from("base"));
+    db.set("changed", 1);
+    db.set("removed", true);
+    db.backup_with_options("chain", BackupOptions::new().incremental_base(true))
+        .unwrap();
+
+    db.set("changed", 2);
+    db.set("added", String::from("first"));
+    db.backup_incremental("chain", "chain_1").unwrap();
+    db.remove(&"removed");
+    db.backup_incremental("chain", "chain_2").unwrap();
+
+    let mut db_copy = SmollDB::load_incremental("chain", &["chain_1", "chain_2"]).unwrap();
+    assert_eq!(db, db_copy);
+    assert!(!db_copy.contains_key(&"removed"));
+
+    db_copy.set("added", String::from("third"));
+    db_copy.backup_incremental("chain", "chain_3").unwrap();
+    let db_copy = SmollDB::load_incremental("chain", &["chain_1", "chain_2", "chain_3"]).unwrap();
+    assert_eq!(
+        *db_copy.get(&"added").unwrap(),
+        DataType::STRING(String::from("third"))
+    );
+    assert!(matches!(SmollDB::load("chain_1"), Err(Error::Incremental)));
+}
+
+#[test]
+#[serial]
+fn incremental_backups_follow_their_base() {
+    let mut db = SmollDB::default();
+    db.set("kept", 1);
+    db.backup("based").unwrap();
+    let result = db.backup_incremental("based", "based_1");
+    assert!(matches!(result, Err(Error::BaseMismatch)));
+    let mut options = BackupOptions::new();
+    options.incremental_base(true);
+    db.backup_with_options("based", &options).unwrap();
+    db.set("added", 2);
+    db.backup_incremental("based", "based_1").unwrap();
+    let db_copy = SmollDB::load_incremental("based", &["based_1"]).unwrap();
+    assert_eq!(db, db_copy);
+
+    db.backup("elsewhere").unwrap();
+    db.set("added", 3);
+    let result = db.backup_incremental("elsewhere", "elsewhere_1");
+    assert!(matches!(result, Err(Error::BaseMismatch)));
+    db.backup_incremental("based", "based_2").unwrap();
+    let db_copy = SmollDB::load_incremental("based", &["based_1", "based_2"]).unwrap();
+    assert_eq!(db, db_copy);
+
+    SmollDB::default().backup("based").unwrap();
+    let result = db.backup_incremental("based", "based_3");
+    assert!(matches!(result, Err(Error::BaseMismatch)));
+}
+
+#[test]
+#[serial]
+fn incremental_backups_reject_broken_chains() {
+    let mut db = SmollDB::default();
+    db.set("key", 1);
+    db.backup_with_options("broken", BackupOptions::new().incremental_base(true))
+        .unwrap();
+    db.set("key", 2);
+    db.backup_incremental("broken", "broken_1").unwrap();
+    db.set("key", 3);
+    db.backup_incremental("broken", "broken_2").unwrap();
+
+    let result = SmollDB::load_incremental("broken", &["broken_2", "broken_1"]);
+    assert!(matches!(result, Err(Error::BrokenDeltaChain(0))));
+
+    db.set("key", 4);
+    db.backup("broken").unwrap();
+    let result = SmollDB::load_incremental("broken", &["broken_1"]);
+    assert!(matches!(result, Err(Error::BrokenDeltaChain(0))));
+}